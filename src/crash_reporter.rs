@@ -73,12 +73,14 @@ impl Display for CrashReporter {
         };
         writeln!(
             f,
-            "shuffling is {}\nexclude lyrics mode is {}\ncurrent song: {} ({}/{})\n",
+            "shuffling is {} (strategy: {}, seed: {})\nexclude lyrics mode is {}\ncurrent song: {} ({}/{})\n",
             if session_settings.shuffle {
                 "enabled"
             } else {
                 "disabled"
             },
+            session_settings.shuffle_strategy.name(),
+            session_settings.get_seed(),
             if session_settings.exclude_lyrics {
                 "enabled"
             } else {
@@ -88,6 +90,12 @@ impl Display for CrashReporter {
             utils::format_duration(&session_settings.song_progress()),
             session_settings.format_song_duration()
         )?;
+        if let Some(filter) = &session_settings.filter {
+            writeln!(f, "filter: {filter}\n")?;
+        }
+        if let Some(playlist) = &session_settings.active_playlist {
+            writeln!(f, "active playlist: {playlist}\n")?;
+        }
         match &self.crash_cause {
             CrashCause::Command(command) => write!(f, "the error occurred as the command '{command}' was processed"),
             CrashCause::KeyEvent(key_event) => write!(f, "the error occurred as the key event '{key_event:?}' was processed"),
@@ -0,0 +1,445 @@
+use std::{fmt::Display, path::Path};
+
+use crate::playlist_settings::PersistentSettings;
+
+#[derive(Clone, Debug)]
+pub struct Filter {
+    name: Option<String>,
+    source: String,
+    expression: Expression,
+}
+
+impl Filter {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let tokens = tokenize(source)?;
+        if tokens.is_empty() {
+            return Err(String::from("the filter is empty"));
+        }
+        let mut parser = Parser { tokens, position: 0 };
+        let expression = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("unexpected '{token}'"));
+        }
+        Ok(Self {
+            name: None,
+            source: source.trim().to_string(),
+            expression,
+        })
+    }
+
+    pub fn named(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    pub fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn get_source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn matches(&self, path: &Path, settings: &PersistentSettings) -> bool {
+        self.expression.evaluate(path, settings)
+    }
+}
+
+impl Display for Filter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{name} ({})", self.source),
+            None => write!(f, "{}", self.source),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Expression {
+    Or(Box<Expression>, Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    Attribute(String),
+    Comparison {
+        attribute: String,
+        operator: Operator,
+        value: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Expression {
+    fn evaluate(&self, path: &Path, settings: &PersistentSettings) -> bool {
+        match self {
+            Expression::Or(left, right) => {
+                left.evaluate(path, settings) || right.evaluate(path, settings)
+            }
+            Expression::And(left, right) => {
+                left.evaluate(path, settings) && right.evaluate(path, settings)
+            }
+            Expression::Not(expression) => !expression.evaluate(path, settings),
+            Expression::Attribute(attribute) => {
+                let song_settings = settings.get_song_settings(&crate::get_song_name(path));
+                match attribute.as_str() {
+                    "starred" => song_settings.is_starred(),
+                    "has_lyrics" | "lyrics" => song_settings.has_lyrics(),
+                    "broken" => song_settings.is_broken,
+                    "rated" => song_settings.rating.is_some(),
+                    tag => song_settings.has_tag(tag),
+                }
+            }
+            Expression::Comparison {
+                attribute,
+                operator,
+                value,
+            } => match get_value(attribute, path, settings) {
+                Value::Number(number) => match value.parse::<f64>() {
+                    Ok(value) => operator.compare(number.total_cmp(&value)),
+                    Err(_) => false,
+                },
+                Value::Text(text) => operator.compare(text.to_lowercase().cmp(&value.to_lowercase())),
+                Value::Tags(tags) => {
+                    let has_tag = tags.iter().any(|tag| tag.eq_ignore_ascii_case(value));
+                    match operator {
+                        Operator::NotEqual => !has_tag,
+                        _ => has_tag,
+                    }
+                }
+            },
+        }
+    }
+}
+
+impl Operator {
+    fn compare(self, ordering: std::cmp::Ordering) -> bool {
+        match self {
+            Operator::Equal => ordering.is_eq(),
+            Operator::NotEqual => ordering.is_ne(),
+            Operator::Less => ordering.is_lt(),
+            Operator::LessOrEqual => ordering.is_le(),
+            Operator::Greater => ordering.is_gt(),
+            Operator::GreaterOrEqual => ordering.is_ge(),
+        }
+    }
+}
+
+enum Value {
+    Number(f64),
+    Text(String),
+    Tags(Vec<String>),
+}
+
+const COMPARABLE_ATTRIBUTES: [&str; 8] = [
+    "rating",
+    "playcount",
+    "play_count",
+    "volume",
+    "name",
+    "song",
+    "folder",
+    "tag",
+];
+
+fn get_value(attribute: &str, path: &Path, settings: &PersistentSettings) -> Value {
+    let song = crate::get_song_name(path);
+    let song_settings = settings.get_song_settings(&song);
+    match attribute {
+        "rating" => Value::Number(song_settings.get_rating() as f64),
+        "playcount" | "play_count" => Value::Number(settings.get_song_play_count(&song) as f64),
+        "volume" => Value::Number((song_settings.song_volume * 100.0).round() as f64),
+        //songs at the top of the playlist folder are in the folder "playlist"
+        "folder" => Value::Text(
+            path.parent()
+                .and_then(Path::file_name)
+                .and_then(|folder| folder.to_str())
+                .unwrap_or_default()
+                .to_string(),
+        ),
+        "tag" => Value::Tags(song_settings.get_tags().to_vec()),
+        _ => Value::Text(song),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Text(String),
+    Operator(String),
+    OpenParenthesis,
+    CloseParenthesis,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Word(word) | Token::Operator(word) => write!(f, "{word}"),
+            Token::Text(text) => write!(f, "\"{text}\""),
+            Token::OpenParenthesis => write!(f, "("),
+            Token::CloseParenthesis => write!(f, ")"),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => (),
+            '(' => tokens.push(Token::OpenParenthesis),
+            ')' => tokens.push(Token::CloseParenthesis),
+            '"' | '\'' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some(other) if other == c => break,
+                        Some(other) => text.push(other),
+                        None => return Err(String::from("a quote is missing its closing quote")),
+                    }
+                }
+                tokens.push(Token::Text(text));
+            }
+            '=' | '!' | '<' | '>' => {
+                let mut operator = c.to_string();
+                if chars.peek() == Some(&'=') {
+                    operator.push(chars.next().unwrap());
+                }
+                if operator == "!" {
+                    return Err(String::from("'!' has to be followed by '='"));
+                }
+                tokens.push(Token::Operator(operator));
+            }
+            _ => {
+                let mut word = c.to_string();
+                while let Some(&other) = chars.peek() {
+                    if other.is_whitespace() || "()\"'=!<>".contains(other) {
+                        break;
+                    }
+                    word.push(other);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word.to_lowercase()));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn next_is_word(&self, word: &str) -> bool {
+        self.peek() == Some(&Token::Word(word.to_string()))
+    }
+
+    fn parse_or(&mut self) -> Result<Expression, String> {
+        let mut expression = self.parse_and()?;
+        while self.next_is_word("or") {
+            self.next();
+            expression = Expression::Or(Box::new(expression), Box::new(self.parse_and()?));
+        }
+        Ok(expression)
+    }
+
+    fn parse_and(&mut self) -> Result<Expression, String> {
+        let mut expression = self.parse_not()?;
+        while self.next_is_word("and") {
+            self.next();
+            expression = Expression::And(Box::new(expression), Box::new(self.parse_not()?));
+        }
+        Ok(expression)
+    }
+
+    fn parse_not(&mut self) -> Result<Expression, String> {
+        if self.next_is_word("not") {
+            self.next();
+            return Ok(Expression::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, String> {
+        match self.next() {
+            Some(Token::OpenParenthesis) => {
+                let expression = self.parse_or()?;
+                match self.next() {
+                    Some(Token::CloseParenthesis) => Ok(expression),
+                    _ => Err(String::from("a parenthesis is missing its closing parenthesis")),
+                }
+            }
+            Some(Token::Word(attribute)) => {
+                let Some(Token::Operator(operator)) = self.peek().cloned() else {
+                    return Ok(Expression::Attribute(attribute));
+                };
+                self.next();
+                if !COMPARABLE_ATTRIBUTES.contains(&attribute.as_str()) {
+                    return Err(format!(
+                        "'{attribute}' can not be compared, comparable attributes are {}",
+                        COMPARABLE_ATTRIBUTES.join(", ")
+                    ));
+                }
+                let operator = match operator.as_str() {
+                    "=" | "==" => Operator::Equal,
+                    "!=" => Operator::NotEqual,
+                    "<" => Operator::Less,
+                    "<=" => Operator::LessOrEqual,
+                    ">" => Operator::Greater,
+                    _ => Operator::GreaterOrEqual,
+                };
+                //a song either has a tag or not, so tags can't be ordered
+                if attribute == "tag" && !matches!(operator, Operator::Equal | Operator::NotEqual) {
+                    return Err(String::from("tags can only be compared with = and !="));
+                }
+                let Some(Token::Word(value) | Token::Text(value)) = self.next() else {
+                    return Err(format!("'{attribute}' is missing a value to compare to"));
+                };
+                Ok(Expression::Comparison {
+                    attribute,
+                    operator,
+                    value,
+                })
+            }
+            Some(token) => Err(format!("unexpected '{token}'")),
+            None => Err(String::from("the filter ended unexpectedly")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::Filter;
+    use crate::playlist_settings::{PersistentSettings, SongSettings};
+
+    const CALM_SONG: &str = "playlist/chill/Calm-Song.mp3";
+    const LOUD_SONG: &str = "playlist/Loud-Song.mp3";
+
+    //"Calm Song" is rated 5 and tagged chill, "Loud Song" is rated 2, has lyrics and a louder volume
+    fn get_settings() -> PersistentSettings {
+        let mut settings = PersistentSettings::default();
+        let mut calm_song = SongSettings::default();
+        calm_song.rating = Some(5);
+        calm_song.add_tag("chill");
+        settings.set_song_settings(String::from("Calm Song"), calm_song);
+        settings.set_song_play_count("Calm Song", 3);
+        let mut loud_song = SongSettings::default();
+        loud_song.rating = Some(2);
+        loud_song.song_volume = 0.8;
+        loud_song.add_tag("lyrics");
+        settings.set_song_settings(String::from("Loud Song"), loud_song);
+        settings
+    }
+
+    fn matches(source: &str, song: &str) -> bool {
+        Filter::parse(source)
+            .expect("the filter should be valid")
+            .matches(Path::new(song), &get_settings())
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert!(matches("chill or rating = 2 and has_lyrics", CALM_SONG));
+        assert!(matches("has_lyrics and rating = 2 or chill", CALM_SONG));
+        assert!(!matches("chill and rating = 2 or has_lyrics", CALM_SONG));
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and() {
+        assert!(!matches("not chill", CALM_SONG));
+        assert!(matches("not chill", LOUD_SONG));
+        assert!(matches("not not chill", CALM_SONG));
+        assert!(!matches("not chill and has_lyrics", CALM_SONG));
+        assert!(matches("not chill and has_lyrics", LOUD_SONG));
+    }
+
+    #[test]
+    fn parentheses_group_expressions() {
+        assert!(!matches("(chill or rating = 2) and has_lyrics", CALM_SONG));
+        assert!(matches("(chill or rating = 2) and has_lyrics", LOUD_SONG));
+        assert!(matches("not (chill and has_lyrics)", CALM_SONG));
+        assert!(matches("((starred))", CALM_SONG));
+    }
+
+    #[test]
+    fn quoted_strings_are_compared_without_case() {
+        assert!(matches("name = \"Calm Song\"", CALM_SONG));
+        assert!(matches("name = 'calm song'", CALM_SONG));
+        assert!(!matches("name = \"Calm Song\"", LOUD_SONG));
+        assert!(matches("song != \"and or not\"", CALM_SONG));
+    }
+
+    #[test]
+    fn every_comparison_operator() {
+        assert!(matches("rating = 5", CALM_SONG));
+        assert!(matches("rating == 5", CALM_SONG));
+        assert!(matches("rating != 4", CALM_SONG));
+        assert!(matches("rating < 3", LOUD_SONG));
+        assert!(!matches("rating < 2", LOUD_SONG));
+        assert!(matches("rating <= 2", LOUD_SONG));
+        assert!(matches("rating > 4", CALM_SONG));
+        assert!(!matches("rating > 5", CALM_SONG));
+        assert!(matches("rating >= 5", CALM_SONG));
+        assert!(matches("playcount >= 3", CALM_SONG));
+        assert!(matches("play_count = 0", LOUD_SONG));
+        assert!(matches("volume = 80", LOUD_SONG));
+        assert!(matches("name < \"d\"", CALM_SONG));
+        assert!(!matches("name < \"d\"", LOUD_SONG));
+        assert!(!matches("rating = high", CALM_SONG));
+    }
+
+    #[test]
+    fn tags_and_folders() {
+        assert!(matches("tag = chill", CALM_SONG));
+        assert!(matches("tag = \"CHILL\"", CALM_SONG));
+        assert!(matches("tag != chill", LOUD_SONG));
+        assert!(matches("chill", CALM_SONG));
+        assert!(matches("folder = chill", CALM_SONG));
+        assert!(matches("folder = playlist", LOUD_SONG));
+        assert!(!matches("folder = chill", LOUD_SONG));
+    }
+
+    #[test]
+    fn invalid_filters_are_rejected() {
+        for source in [
+            "",
+            "   ",
+            "rating >",
+            "rating ! 3",
+            "(chill",
+            "chill)",
+            "chill and",
+            "and chill",
+            "not",
+            "name = \"Calm Song",
+            "starred = 3",
+            "tag < chill",
+            "tag >= chill",
+            "rating = 5 5",
+        ] {
+            assert!(Filter::parse(source).is_err(), "'{source}' should be invalid");
+        }
+    }
+}
@@ -1,11 +1,27 @@
-use std::{fs, path::PathBuf, process, time::Instant};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, Instant},
+};
 
+use chrono::{Local, TimeZone};
 use rdev::{Event, EventType, Key};
 use rodio::{Sink, Source};
 
 use crate::{
     crash_reporter::CrashReporter,
-    playlist_settings::{self, AfterSong, SessionSettings},
+    filter::Filter,
+    history::{self, ListenEnd},
+    last_session::LastSession,
+    library, library_import,
+    playlist_file::{self, PlaylistFormat},
+    playlist_settings::{
+        self, AfterSong, PlayThreshold, ProbabilityGrowth, RotationConstraints, SessionSettings,
+    },
+    settings_csv::{self, SettingsImport},
+    shuffle_strategy,
+    stats::{ExportFormat, ListeningStats, StatsRange},
     utils,
 };
 
@@ -101,12 +117,112 @@ pub fn handle_console_commands(
         "starred" | "starredsongs" => {
             print_starred_songs(paths);
         }
+        "unrate" | "removerating" => {
+            unrate(session_settings);
+        }
+        "ratings" | "ratedsongs" => {
+            print_ratings(paths);
+        }
+        "ratingweights" => {
+            print_rating_weights();
+        }
+        "rate+" | "rating+" => {
+            increase_rating(session_settings);
+        }
+        "rate-" | "rating-" => {
+            decrease_rating(session_settings);
+        }
+        "dislike" => {
+            set_rating(session_settings, playlist_settings::DISLIKE_RATING);
+        }
+        "filter" => {
+            print_filter(session_settings, paths);
+        }
+        "clearfilter" | "removefilter" | "nofilter" => {
+            clear_filter(session_settings);
+        }
+        "rotation" | "rotationconstraints" => {
+            println!("{}", playlist_settings::get_persistent_settings().rotation);
+        }
+        "skips" | "skippedsongs" => {
+            print_skipped_songs(paths);
+        }
+        "playedtoday" | "today" => {
+            print_played_today();
+        }
+        "enablescrobbling" | "enablescrobblelog" => {
+            set_scrobble_log(true);
+        }
+        "disablescrobbling" | "disablescrobblelog" => {
+            set_scrobble_log(false);
+        }
+        "confirmimport" | "applyimport" => {
+            confirm_settings_import(session_settings);
+        }
+        "cancelimport" => {
+            if session_settings.pending_settings_import.take().is_some() {
+                println!("the import was cancelled");
+            } else {
+                println!("there is no import waiting to be confirmed");
+            }
+        }
+        "playthreshold" => {
+            print_play_threshold();
+        }
+        "skippenalty" => {
+            print_skip_penalty();
+        }
+        "seed" => {
+            println!("shuffle seed: {}", session_settings.get_seed());
+        }
+        "shufflestrategies" | "strategies" => {
+            print_shuffle_strategies(session_settings);
+        }
+        "playlists" | "pls" => {
+            print_playlists();
+        }
+        "playlibrary" | "leaveplaylist" | "exitplaylist" => {
+            leave_playlist(session_settings);
+        }
+        "smartplaylists" | "sps" => {
+            print_smart_playlists(paths);
+        }
+        "tags" => {
+            print_tags(paths);
+        }
         "haslyrics" | "setlyrics" => {
             set_lyrics(session_settings);
         }
         "hasnolyrics" | "setnolyrics" => {
             set_no_lyrics(session_settings);
         }
+        "rememberposition" | "resumeposition" => {
+            set_resume_position(session_settings, true);
+        }
+        "forgetposition" | "noresumeposition" => {
+            set_resume_position(session_settings, false);
+        }
+        "bookmarks" | "marks" => {
+            print_bookmarks(session_settings);
+        }
+        "loopa" => {
+            set_loop_start(audio_player, session_settings);
+        }
+        "loopb" => {
+            set_loop_end(audio_player, session_settings);
+        }
+        "stoploop" | "loopoff" | "endloop" => {
+            stop_loop(session_settings);
+        }
+        "trimstart" => {
+            trim_start(audio_player, paths, session_settings);
+        }
+        "trimend" => {
+            trim_end(audio_player, paths, session_settings);
+        }
+        "resettrim" | "untrim" => {
+            reset_trim(audio_player, paths, session_settings);
+        }
         "nolyrics" | "lyricsoff" | "nolyricsmode" | "deactivatelyrics" | "excludelyrics"
         | "focusmode" | "focus" | "focusmodeon" => {
             turn_off_lyrics_mode(session_settings);
@@ -128,16 +244,182 @@ pub fn handle_console_commands(
             print_progress(session_settings);
         }
         "songprobabilities" | "probabilities" | "showprobabilities" => {
-            print_song_probabilities(paths);
+            print_song_probabilities(session_settings, paths);
         }
         "playcount" => {
             print_play_count(&session_settings.current_song_name);
         }
+        "checklibrary" | "librarycheck" => {
+            check_library(paths);
+        }
+        "prunelibrary" => {
+            library::prune_orphaned_entries(paths);
+        }
+        "relinklibrary" => {
+            library::relink_orphaned_entries(paths);
+        }
         "commands" | "help" => {
             print_commands();
         }
+        "newmachineid" => {
+            new_machine_id();
+        }
         "terminate" | "exit" | "close" => {
-            exit_program(crash_reporter);
+            exit_program(audio_player, session_settings, paths, crash_reporter);
+        }
+        msg if msg.starts_with("deletemark") => {
+            delete_bookmark(&utils::get_arguments(input_buffer, "deletemark"), session_settings);
+        }
+        msg if msg.starts_with("unmark") => {
+            delete_bookmark(&utils::get_arguments(input_buffer, "unmark"), session_settings);
+        }
+        msg if msg.starts_with("mark") => {
+            set_bookmark(&utils::get_arguments(input_buffer, "mark"), audio_player, session_settings);
+        }
+        msg if msg.starts_with("goto") => {
+            goto_bookmark(&utils::get_arguments(input_buffer, "goto"), audio_player, session_settings);
+        }
+        msg if msg.starts_with("loop") => {
+            loop_between_bookmarks(&utils::get_arguments(input_buffer, "loop"), audio_player, session_settings);
+        }
+        msg if msg.starts_with("replaygapsongs") => {
+            let value = msg.split_once("replaygapsongs").unwrap().1;
+            set_rotation_constraint(value, |rotation, value| rotation.min_songs_between = value);
+        }
+        msg if msg.starts_with("replaygapminutes") => {
+            let value = msg.split_once("replaygapminutes").unwrap().1;
+            set_rotation_constraint(value, |rotation, value| rotation.min_minutes_between = value);
+        }
+        msg if msg.starts_with("maxplaysperday") => {
+            let value = msg.split_once("maxplaysperday").unwrap().1;
+            set_rotation_constraint(value, |rotation, value| rotation.max_plays_per_day = value);
+        }
+        msg if msg.starts_with("exportstats") => {
+            let arguments = utils::get_arguments(input_buffer, "exportstats");
+            export_stats(&arguments, paths);
+        }
+        msg if msg.starts_with("stats") => {
+            let range = utils::get_arguments(input_buffer, "stats");
+            print_stats(&range, paths);
+        }
+        msg if msg.starts_with("lastplayed") => {
+            let song = utils::get_arguments(input_buffer, "lastplayed");
+            print_last_played(&song, session_settings, paths);
+        }
+        msg if msg.starts_with("playthresholdpercent") => {
+            let value = msg.split_once("playthresholdpercent").unwrap().1;
+            set_play_threshold(value, 100, |threshold, value| threshold.percent = value);
+        }
+        msg if msg.starts_with("playthresholdminutes") => {
+            let value = msg.split_once("playthresholdminutes").unwrap().1;
+            set_play_threshold(value, u32::MAX, |threshold, value| threshold.minutes = value);
+        }
+        msg if msg.starts_with("skippenalty") => {
+            let penalty = msg.split_once("skippenalty").unwrap().1;
+            set_skip_penalty(penalty);
+        }
+        msg if msg.starts_with("probabilitygrowth") => {
+            let growth = msg.split_once("probabilitygrowth").unwrap().1;
+            set_probability_growth(growth);
+        }
+        msg if msg.starts_with("seed") => {
+            let seed = msg.split_once("seed").unwrap().1;
+            set_seed(seed, session_settings);
+        }
+        msg if msg.starts_with("shufflestrategy") => {
+            let strategy = msg.split_once("shufflestrategy").unwrap().1;
+            set_shuffle_strategy(strategy, session_settings);
+        }
+        msg if msg.starts_with("strategy") => {
+            let strategy = msg.split_once("strategy").unwrap().1;
+            set_shuffle_strategy(strategy, session_settings);
+        }
+        msg if msg.starts_with("mergesettings") => {
+            merge_settings(&utils::get_arguments(input_buffer, "mergesettings"));
+        }
+        msg if msg.starts_with("importlibrary") => {
+            import_library(&utils::get_arguments(input_buffer, "importlibrary"), paths);
+        }
+        msg if msg.starts_with("exportsettings") => {
+            export_settings(&utils::get_arguments(input_buffer, "exportsettings"), paths);
+        }
+        msg if msg.starts_with("importsettings") => {
+            import_settings(&utils::get_arguments(input_buffer, "importsettings"), session_settings, paths);
+        }
+        msg if msg.starts_with("exportplaylist") => {
+            export_playlist(&utils::get_arguments(input_buffer, "exportplaylist"), session_settings, paths);
+        }
+        msg if msg.starts_with("importplaylist") => {
+            import_playlist(&utils::get_arguments(input_buffer, "importplaylist"), false, paths);
+        }
+        msg if msg.starts_with("replaceplaylist") => {
+            import_playlist(&utils::get_arguments(input_buffer, "replaceplaylist"), true, paths);
+        }
+        msg if msg.starts_with("queueplaylist") => {
+            queue_playlist(&utils::get_arguments(input_buffer, "queueplaylist"), session_settings, paths);
+        }
+        msg if msg.starts_with("createplaylist") => {
+            create_playlist(&utils::get_arguments(input_buffer, "createplaylist"));
+        }
+        msg if msg.starts_with("deleteplaylist") => {
+            delete_playlist(&utils::get_arguments(input_buffer, "deleteplaylist"), session_settings);
+        }
+        msg if msg.starts_with("playplaylist") => {
+            play_playlist(&utils::get_arguments(input_buffer, "playplaylist"), session_settings, paths);
+        }
+        //"play list..." is left to choosing a song like any other "play [song]"
+        msg if msg.starts_with("playlist")
+            && input_buffer
+                .split_whitespace()
+                .next()
+                .is_some_and(|command| command.eq_ignore_ascii_case("playlist")) =>
+        {
+            print_playlist_songs(&utils::get_arguments(input_buffer, "playlist"), paths);
+        }
+        msg if msg.starts_with("add") => {
+            add_to_playlist(&utils::get_arguments(input_buffer, "add"), session_settings, paths);
+        }
+        msg if msg.starts_with("remove") && msg.contains("from") => {
+            remove_from_playlist(&utils::get_arguments(input_buffer, "remove"), session_settings);
+        }
+        msg if msg.starts_with("move") => {
+            move_in_playlist(&utils::get_arguments(input_buffer, "move"));
+        }
+        msg if msg.starts_with("savefilteras") => {
+            save_smart_playlist(&utils::get_arguments(input_buffer, "savefilteras"), session_settings);
+        }
+        msg if msg.starts_with("deletesmartplaylist") => {
+            delete_smart_playlist(&utils::get_arguments(input_buffer, "deletesmartplaylist"));
+        }
+        msg if msg.starts_with("smartplaylist") => {
+            play_smart_playlist(&utils::get_arguments(input_buffer, "smartplaylist"), session_settings, paths);
+        }
+        msg if msg.starts_with("sp") => {
+            play_smart_playlist(&utils::get_arguments(input_buffer, "sp"), session_settings, paths);
+        }
+        msg if msg.starts_with("filter") => {
+            set_filter(&utils::get_arguments(input_buffer, "filter"), session_settings, paths);
+        }
+        msg if msg.starts_with("songstagged") => {
+            let tag = msg.split_once("songstagged").unwrap().1;
+            print_tagged_songs(tag, paths);
+        }
+        msg if msg.starts_with("tagged") => {
+            let tag = msg.split_once("tagged").unwrap().1;
+            print_tagged_songs(tag, paths);
+        }
+        msg if msg.starts_with("untag") => {
+            untag(&utils::get_arguments(input_buffer, "untag"), session_settings, paths);
+        }
+        msg if msg.starts_with("tag") => {
+            tag(&utils::get_arguments(input_buffer, "tag"), session_settings, paths);
+        }
+        msg if msg.starts_with("ratingweight") => {
+            set_rating_weight(input_buffer);
+        }
+        msg if msg.starts_with("rate") => {
+            let rating = msg.split_once("rate").unwrap().1;
+            rate(rating, session_settings);
         }
         msg if msg.starts_with("choosesong") => {
             let new_song = msg.split_once("choosesong").unwrap().1;
@@ -226,11 +508,17 @@ pub fn handle_key_event(
         Key::F9 => {
             switch_lyrics_mode(session_settings);
         }
+        Key::F2 => {
+            decrease_rating(session_settings);
+        }
+        Key::F3 => {
+            increase_rating(session_settings);
+        }
         _ => (),
     }
 }
 
-fn pause(session_settings: &mut SessionSettings, audio_player: &Sink) {
+pub fn pause(session_settings: &mut SessionSettings, audio_player: &Sink) {
     if !audio_player.is_paused() {
         audio_player.pause();
         session_settings.add_song_progress(session_settings.duration_start.elapsed());
@@ -388,6 +676,294 @@ fn switch_shuffling(session_settings: &mut SessionSettings, paths: &[PathBuf]) {
     playlist_settings::update_settings(&persistent_settings);
 }
 
+fn set_shuffle_strategy(strategy: &str, session_settings: &mut SessionSettings) {
+    match shuffle_strategy::from_name(strategy) {
+        Some(strategy) => {
+            session_settings.shuffle_strategy = strategy;
+            println!(
+                "songs are now chosen with the {} shuffle strategy",
+                session_settings.shuffle_strategy.name()
+            );
+            if !session_settings.shuffle {
+                println!("shuffling is disabled, enable it with 'enable shuffle' to use the strategy");
+            }
+        }
+        None => println!(
+            "unknown shuffle strategy, available strategies are {}",
+            shuffle_strategy::STRATEGY_NAMES.join(", ")
+        ),
+    }
+}
+
+fn set_rotation_constraint(value: &str, set: impl Fn(&mut RotationConstraints, u32)) {
+    let Ok(value) = value.parse::<u32>() else {
+        println!("this command requires a positive integer like \"replay gap songs 10\" (0 disables the constraint)");
+        return;
+    };
+    let mut settings = playlist_settings::get_persistent_settings();
+    set(&mut settings.rotation, value);
+    playlist_settings::update_settings(&settings);
+    println!("{}", settings.rotation);
+}
+
+fn record_skip(audio_player: &Sink, session_settings: &SessionSettings) {
+    //a song that already finished was not skipped
+    if audio_player.empty() || session_settings.current_song_name.is_empty() {
+        return;
+    }
+    let position = session_settings.song_position(audio_player.is_paused());
+    let mut settings = playlist_settings::get_persistent_settings();
+    let skip_position = u32::try_from(position.as_secs()).unwrap_or(u32::MAX);
+    settings.record_skip(&session_settings.current_song_name, skip_position);
+    playlist_settings::update_settings(&settings);
+    crate::end_listen(session_settings, position, ListenEnd::Skipped);
+}
+
+fn interrupt_listen(audio_player: &Sink, session_settings: &SessionSettings) {
+    if audio_player.empty() {
+        return;
+    }
+    let position = session_settings.song_position(audio_player.is_paused());
+    crate::end_listen(session_settings, position, ListenEnd::Interrupted);
+}
+
+fn print_played_today() {
+    let start_of_day = utils::get_start_of_day(Local::now().timestamp());
+    let listens = history::get_history_since(start_of_day);
+    if listens.is_empty() {
+        println!("nothing has been played today");
+        return;
+    }
+    let listened_seconds = listens.iter().map(|listen| listen.listened_seconds).sum::<u64>();
+    println!(
+        "{} songs played today, {} listened",
+        listens.len(),
+        utils::format_duration(&Duration::from_secs(listened_seconds))
+    );
+    for listen in listens {
+        let started = Local
+            .timestamp_opt(listen.started, 0)
+            .single()
+            .map(|time| time.format("%H:%M").to_string())
+            .unwrap_or_default();
+        println!(
+            "{started} {} - {} ({})",
+            listen.song,
+            utils::format_duration(&Duration::from_secs(listen.listened_seconds)),
+            listen.end
+        );
+    }
+}
+
+fn print_last_played(song: &str, session_settings: &SessionSettings, paths: &[PathBuf]) {
+    let song = if song.is_empty() {
+        session_settings.current_song_name.clone()
+    } else {
+        let Some(index) = find_song(song, paths) else {
+            return;
+        };
+        crate::get_song_name(&paths[index])
+    };
+    let history = history::get_history();
+    let Some(listen) = history.iter().rev().find(|listen| listen.song == song) else {
+        //songs played before the history existed still have their last played time
+        match playlist_settings::get_persistent_settings().get_song_last_played(&song) {
+            Some(last_played) => println!("{song} was last played {}", format_timestamp(last_played)),
+            None => println!("{song} has not been played yet"),
+        }
+        return;
+    };
+    println!(
+        "{song} was last played {}, listened for {} ({})",
+        format_timestamp(listen.started),
+        utils::format_duration(&Duration::from_secs(listen.listened_seconds)),
+        listen.end
+    );
+    let modes = listen.get_modes();
+    if !modes.is_empty() {
+        println!("modes: {}", modes.join(", "));
+    }
+    let listens = history.iter().filter(|listen| listen.song == song).count();
+    println!("{song} appears {listens} times in the listening history");
+}
+
+fn format_timestamp(timestamp: i64) -> String {
+    Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+fn print_stats(range: &str, paths: &[PathBuf]) {
+    let Some(range) = StatsRange::parse(range) else {
+        println!("the range has to be today, week, month, year, a year like 2024 or all");
+        return;
+    };
+    ListeningStats::collect(range, paths).print();
+}
+
+fn export_stats(arguments: &str, paths: &[PathBuf]) {
+    let (file, range) = arguments.split_once(' ').unwrap_or((arguments, ""));
+    let Some(format) = ExportFormat::from_file_name(file) else {
+        println!("this command requires a file ending with .html or .md like \"export stats wrapped.html year\"");
+        return;
+    };
+    let Some(range) = StatsRange::parse(range) else {
+        println!("the range has to be today, week, month, year, a year like 2024 or all");
+        return;
+    };
+    match ListeningStats::collect(range, paths).export(file, &format) {
+        Ok(()) => println!("exported the listening stats ({range}) to {file}"),
+        Err(error) => println!("{error}"),
+    }
+}
+
+fn set_scrobble_log(enabled: bool) {
+    let mut settings = playlist_settings::get_persistent_settings();
+    settings.scrobble_log = enabled;
+    playlist_settings::update_settings(&settings);
+    if enabled {
+        println!("plays will be written to .scrobbler.log");
+    } else {
+        println!("plays will no longer be written to .scrobbler.log");
+    }
+}
+
+fn print_play_threshold() {
+    println!("{}", playlist_settings::get_persistent_settings().play_threshold);
+}
+
+fn set_play_threshold(value: &str, maximum: u32, set: impl Fn(&mut PlayThreshold, u32)) {
+    let value = match value.trim_end_matches('%').parse::<u32>() {
+        Ok(value) if value <= maximum => value,
+        _ => {
+            println!("this command requires a positive integer up to {maximum} like \"play threshold percent 50\" (0 disables the threshold)");
+            return;
+        }
+    };
+    let mut settings = playlist_settings::get_persistent_settings();
+    set(&mut settings.play_threshold, value);
+    playlist_settings::update_settings(&settings);
+    print_play_threshold();
+}
+
+fn print_skipped_songs(paths: &[PathBuf]) {
+    let settings = playlist_settings::get_persistent_settings();
+    let mut skipped_songs = paths
+        .iter()
+        .map(|path| crate::get_song_name(path))
+        .filter(|song| settings.get_skip_stats(song).count > 0)
+        .collect::<Vec<String>>();
+    if skipped_songs.is_empty() {
+        println!("no songs have been skipped");
+        return;
+    }
+    skipped_songs.sort_by(|a, b| {
+        settings
+            .get_skip_rate(b)
+            .total_cmp(&settings.get_skip_rate(a))
+            .then_with(|| settings.get_skip_stats(b).count.cmp(&settings.get_skip_stats(a).count))
+    });
+    print_skip_penalty();
+    for song in skipped_songs {
+        let skips = settings.get_skip_stats(&song);
+        let average_position = skips
+            .average_position()
+            .map(|position| utils::format_duration(&Duration::from_secs(position)))
+            .unwrap_or_default();
+        println!(
+            "{song} - skipped {} times ({:.0}% of listens), on average at {average_position}, weight x{:.2}",
+            skips.count,
+            settings.get_skip_rate(&song) * 100.0,
+            settings.get_skip_multiplier(&song)
+        );
+    }
+}
+
+fn print_skip_penalty() {
+    let penalty = playlist_settings::get_persistent_settings().skip_penalty;
+    if penalty == 0 {
+        println!("skipped songs are not penalised");
+    } else {
+        println!("a song that is always skipped loses {penalty}% of its weight");
+    }
+}
+
+//used by "skip penalty on"
+const DEFAULT_SKIP_PENALTY: u32 = 50;
+
+fn set_skip_penalty(penalty: &str) {
+    let penalty = match penalty {
+        "off" => 0,
+        "on" => DEFAULT_SKIP_PENALTY,
+        penalty => match penalty.trim_end_matches('%').parse::<u32>() {
+            Ok(penalty) if penalty <= 100 => penalty,
+            _ => {
+                println!("this command requires a percentage from 0 to 100, \"on\" or \"off\"");
+                return;
+            }
+        },
+    };
+    let mut settings = playlist_settings::get_persistent_settings();
+    settings.skip_penalty = penalty;
+    playlist_settings::update_settings(&settings);
+    print_skip_penalty();
+}
+
+fn set_probability_growth(growth: &str) {
+    let growth = match growth {
+        "linear" => Some(ProbabilityGrowth::Linear),
+        "logarithmic" | "log" => Some(ProbabilityGrowth::Logarithmic),
+        growth => {
+            if let Some(cap) = growth.strip_prefix("capped") {
+                cap.parse::<u32>()
+                    .ok()
+                    .filter(|cap| *cap > 0)
+                    .map(ProbabilityGrowth::Capped)
+            } else if let Some(hours) = growth.strip_prefix("time") {
+                hours
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|hours| *hours > 0.0)
+                    .map(ProbabilityGrowth::Time)
+            } else {
+                None
+            }
+        }
+    };
+    let Some(growth) = growth else {
+        println!("this command requires linear, logarithmic, capped [maximum] or time [hours] like \"probability growth time 24\"");
+        return;
+    };
+    let mut settings = playlist_settings::get_persistent_settings();
+    settings.probability_growth = growth;
+    playlist_settings::update_settings(&settings);
+    println!("probability growth: {growth}");
+}
+
+fn set_seed(seed: &str, session_settings: &mut SessionSettings) {
+    match seed.parse::<u64>() {
+        Ok(seed) => {
+            session_settings.set_seed(seed);
+            println!("shuffle seed set to {seed}, the next songs are chosen with this seed");
+        }
+        Err(_) => println!("this command requires a positive integer as seed like \"seed 42\""),
+    }
+}
+
+fn print_shuffle_strategies(session_settings: &SessionSettings) {
+    println!("weighted     - songs get more likely to be chosen the longer they weren't played, higher rated songs are chosen more often");
+    println!("random       - every song is equally likely to be chosen");
+    println!("bag          - every song is played once before any song is played again");
+    println!("least-recent - the song that was played the longest time ago is chosen");
+    println!("least-played - the song that was played the fewest times is chosen");
+    println!(
+        "current strategy: {}",
+        session_settings.shuffle_strategy.name()
+    );
+}
+
 fn reset_probabilities(paths: &[PathBuf]) {
     let mut persistent_settings = playlist_settings::get_persistent_settings();
     for i in 0..paths.len() {
@@ -397,21 +973,14 @@ fn reset_probabilities(paths: &[PathBuf]) {
 }
 
 fn star(session_settings: &SessionSettings) {
-    let mut settings = playlist_settings::get_persistent_settings()
-        .get_song_settings(&session_settings.current_song_name);
-    settings.starred = true;
-    println!(
-        "{} is now starred. It will get chosen twice as often",
-        session_settings.current_song_name
-    );
-    playlist_settings::update_song_settings(session_settings.current_song_name.clone(), settings);
+    set_rating(session_settings, playlist_settings::MAX_RATING);
 }
 
 fn unstar(session_settings: &SessionSettings) {
     let mut settings = playlist_settings::get_persistent_settings()
         .get_song_settings(&session_settings.current_song_name);
-    if settings.starred {
-        settings.starred = false;
+    if settings.is_starred() {
+        settings.rating = None;
         println!(
             "{} is no longer starred",
             session_settings.current_song_name
@@ -422,21 +991,122 @@ fn unstar(session_settings: &SessionSettings) {
     playlist_settings::update_song_settings(session_settings.current_song_name.clone(), settings);
 }
 
+fn rate(rating: &str, session_settings: &SessionSettings) {
+    let rating = match rating {
+        "dislike" | "disliked" => Ok(playlist_settings::DISLIKE_RATING),
+        rating => rating.parse::<u8>(),
+    };
+    match rating {
+        Ok(rating) if rating <= playlist_settings::MAX_RATING => {
+            set_rating(session_settings, rating);
+        }
+        _ => println!(
+            "this command requires a rating from 1 to {} or 0 / \"dislike\"",
+            playlist_settings::MAX_RATING
+        ),
+    }
+}
+
+fn increase_rating(session_settings: &SessionSettings) {
+    let rating = playlist_settings::get_persistent_settings()
+        .get_song_settings(&session_settings.current_song_name)
+        .get_rating();
+    set_rating(session_settings, (rating + 1).min(playlist_settings::MAX_RATING));
+}
+
+fn decrease_rating(session_settings: &SessionSettings) {
+    let rating = playlist_settings::get_persistent_settings()
+        .get_song_settings(&session_settings.current_song_name)
+        .get_rating();
+    set_rating(session_settings, rating.saturating_sub(1));
+}
+
+fn set_rating(session_settings: &SessionSettings, rating: u8) {
+    let persistent_settings = playlist_settings::get_persistent_settings();
+    let mut settings = persistent_settings.get_song_settings(&session_settings.current_song_name);
+    settings.rating = Some(rating);
+    let factor = persistent_settings.get_rating_weight(rating) as f32
+        / persistent_settings.get_rating_weight(playlist_settings::DEFAULT_RATING).max(1) as f32;
+    println!(
+        "{} is now rated {}. It will get chosen {factor:.2} times as often as an unrated song",
+        session_settings.current_song_name,
+        playlist_settings::format_rating(rating)
+    );
+    playlist_settings::update_song_settings(session_settings.current_song_name.clone(), settings);
+}
+
+fn unrate(session_settings: &SessionSettings) {
+    let mut settings = playlist_settings::get_persistent_settings()
+        .get_song_settings(&session_settings.current_song_name);
+    settings.rating = None;
+    println!("{} is no longer rated", session_settings.current_song_name);
+    playlist_settings::update_song_settings(session_settings.current_song_name.clone(), settings);
+}
+
+fn set_rating_weight(input_buffer: &str) {
+    let arguments = input_buffer.split_whitespace().collect::<Vec<&str>>();
+    let [.., rating, weight] = arguments.as_slice() else {
+        println!("this command requires a rating and a weight like \"rating weight 5 8\"");
+        return;
+    };
+    let rating = match *rating {
+        "dislike" | "disliked" => Ok(playlist_settings::DISLIKE_RATING),
+        rating => rating.parse::<u8>(),
+    };
+    match (rating, weight.parse::<u32>()) {
+        //the weights of the other ratings are relative to the default rating, so it can't be excluded
+        (Ok(playlist_settings::DEFAULT_RATING), Ok(0)) => println!(
+            "unrated songs need a weight above 0, the weights of the other ratings are relative to it"
+        ),
+        (Ok(rating), Ok(weight)) if rating <= playlist_settings::MAX_RATING => {
+            let mut settings = playlist_settings::get_persistent_settings();
+            settings.set_rating_weight(rating, weight);
+            playlist_settings::update_settings(&settings);
+            println!(
+                "songs rated {} now have a selection weight of {weight}",
+                playlist_settings::format_rating(rating)
+            );
+        }
+        _ => println!("this command requires a rating and a weight like \"rating weight 5 8\""),
+    }
+}
+
+fn print_rating_weights() {
+    let settings = playlist_settings::get_persistent_settings();
+    for rating in (playlist_settings::DISLIKE_RATING..=playlist_settings::MAX_RATING).rev() {
+        println!(
+            "{} - {}",
+            playlist_settings::format_rating(rating),
+            settings.get_rating_weight(rating)
+        );
+    }
+}
+
 fn print_starred_songs(paths: &[PathBuf]) {
     println!("starred songs:");
     let settings = playlist_settings::get_persistent_settings();
     for path in paths {
         let song = crate::get_song_name(path);
-        if settings.get_song_settings(&song).starred {
+        if settings.get_song_settings(&song).is_starred() {
             println!("{song}");
         }
     }
 }
 
+fn print_ratings(paths: &[PathBuf]) {
+    let settings = playlist_settings::get_persistent_settings();
+    for path in paths {
+        let song = crate::get_song_name(path);
+        if let Some(rating) = settings.get_song_settings(&song).rating {
+            println!("{} - {song}", playlist_settings::format_rating(rating));
+        }
+    }
+}
+
 fn set_lyrics(session_settings: &SessionSettings) {
     let mut settings = playlist_settings::get_persistent_settings()
         .get_song_settings(&session_settings.current_song_name);
-    settings.has_lyrics = true;
+    settings.add_tag(playlist_settings::LYRICS_TAG);
     println!(
         "{} is set to have lyrics",
         session_settings.current_song_name
@@ -447,8 +1117,7 @@ fn set_lyrics(session_settings: &SessionSettings) {
 fn set_no_lyrics(session_settings: &SessionSettings) {
     let mut settings = playlist_settings::get_persistent_settings()
         .get_song_settings(&session_settings.current_song_name);
-    if settings.has_lyrics {
-        settings.has_lyrics = false;
+    if settings.remove_tag(playlist_settings::LYRICS_TAG) {
         println!(
             "{} is no longer set to have lyrics",
             session_settings.current_song_name
@@ -462,12 +1131,245 @@ fn set_no_lyrics(session_settings: &SessionSettings) {
     playlist_settings::update_song_settings(session_settings.current_song_name.clone(), settings);
 }
 
-fn turn_off_lyrics_mode(session_settings: &mut SessionSettings) {
-    session_settings.exclude_lyrics = true;
-    println!("Songs with lyrics will now be excluded from the playlist");
+fn set_resume_position(session_settings: &SessionSettings, resume_position: bool) {
+    let song = &session_settings.current_song_name;
+    let mut settings = playlist_settings::get_persistent_settings();
+    let mut song_settings = settings.get_song_settings(song);
+    if song_settings.resume_position == resume_position {
+        if resume_position {
+            println!("{song} already continues where it was stopped");
+        } else {
+            println!("{song} already starts from the beginning");
+        }
+        return;
+    }
+    song_settings.resume_position = resume_position;
+    settings.set_song_settings(song.clone(), song_settings);
+    if resume_position {
+        println!("{song} will continue where it was stopped when it is skipped or the player is closed");
+    } else {
+        settings.set_song_position(song, None);
+        println!("{song} will start from the beginning again");
+    }
+    playlist_settings::update_settings(&settings);
 }
 
-fn turn_on_lyrics_mode(session_settings: &mut SessionSettings) {
+fn set_bookmark(name: &str, audio_player: &Sink, session_settings: &SessionSettings) {
+    if name.is_empty() {
+        println!("this command requires the name of the bookmark, like: mark drop");
+        return;
+    }
+    if audio_player.empty() {
+        println!("no song is playing");
+        return;
+    }
+    let song = &session_settings.current_song_name;
+    let position = session_settings.song_position(audio_player.is_paused());
+    let mut settings = playlist_settings::get_persistent_settings();
+    settings.set_bookmark(song, name, position);
+    playlist_settings::update_settings(&settings);
+    println!("marked '{name}' at {} in {song}", utils::format_duration(&position));
+}
+
+fn delete_bookmark(name: &str, session_settings: &SessionSettings) {
+    let song = &session_settings.current_song_name;
+    let mut settings = playlist_settings::get_persistent_settings();
+    if settings.remove_bookmark(song, name) {
+        playlist_settings::update_settings(&settings);
+        println!("deleted the bookmark '{name}' of {song}");
+    } else {
+        println!("{song} has no bookmark named '{name}'");
+    }
+}
+
+fn goto_bookmark(name: &str, audio_player: &Sink, session_settings: &mut SessionSettings) {
+    let Some(position) = find_bookmark(name, session_settings) else {
+        return;
+    };
+    if crate::seek_song(audio_player, session_settings, position) {
+        println!("jumped to '{name}' at {}", utils::format_duration(&position));
+    }
+}
+
+fn find_bookmark(name: &str, session_settings: &SessionSettings) -> Option<Duration> {
+    let song = &session_settings.current_song_name;
+    let bookmark = playlist_settings::get_persistent_settings().get_bookmark(song, name);
+    if bookmark.is_none() {
+        println!("{song} has no bookmark named '{name}', 'bookmarks' lists the bookmarks of the song");
+    }
+    bookmark
+}
+
+fn print_bookmarks(session_settings: &SessionSettings) {
+    let song = &session_settings.current_song_name;
+    let settings = playlist_settings::get_persistent_settings();
+    let bookmarks = settings.get_bookmarks(song);
+    if bookmarks.is_empty() {
+        println!("{song} has no bookmarks, 'mark [name]' adds one at the current position");
+        return;
+    }
+    println!("bookmarks of {song}:");
+    for (name, position) in bookmarks {
+        println!("{} - {name}", utils::format_duration(&Duration::from_secs(*position)));
+    }
+    if let (Some(loop_start), Some(loop_end)) = (session_settings.loop_start, session_settings.loop_end) {
+        println!(
+            "looping from {} to {}",
+            utils::format_duration(&loop_start),
+            utils::format_duration(&loop_end)
+        );
+    }
+}
+
+fn set_loop_start(audio_player: &Sink, session_settings: &mut SessionSettings) {
+    let position = session_settings.song_position(audio_player.is_paused());
+    session_settings.loop_start = Some(position);
+    session_settings.loop_end = None;
+    println!(
+        "the loop starts at {}, type 'loop b' at its end",
+        utils::format_duration(&position)
+    );
+}
+
+fn set_loop_end(audio_player: &Sink, session_settings: &mut SessionSettings) {
+    let Some(loop_start) = session_settings.loop_start else {
+        println!("type 'loop a' at the start of the loop first");
+        return;
+    };
+    let position = session_settings.song_position(audio_player.is_paused());
+    start_loop(loop_start, position, audio_player, session_settings);
+}
+
+fn loop_between_bookmarks(arguments: &str, audio_player: &Sink, session_settings: &mut SessionSettings) {
+    let names = arguments.split_whitespace().collect::<Vec<&str>>();
+    let [start, end] = names.as_slice() else {
+        println!("this command requires two bookmarks, like: loop verse chorus");
+        return;
+    };
+    let (Some(loop_start), Some(loop_end)) = (
+        find_bookmark(start, session_settings),
+        find_bookmark(end, session_settings),
+    ) else {
+        return;
+    };
+    start_loop(loop_start, loop_end, audio_player, session_settings);
+}
+
+fn start_loop(
+    loop_start: Duration,
+    loop_end: Duration,
+    audio_player: &Sink,
+    session_settings: &mut SessionSettings,
+) {
+    if loop_end <= loop_start {
+        println!("the end of the loop has to be after its start");
+        return;
+    }
+    if !crate::seek_song(audio_player, session_settings, loop_start) {
+        return;
+    }
+    session_settings.loop_start = Some(loop_start);
+    session_settings.loop_end = Some(loop_end);
+    println!(
+        "looping from {} to {} until 'stop loop'",
+        utils::format_duration(&loop_start),
+        utils::format_duration(&loop_end)
+    );
+}
+
+fn stop_loop(session_settings: &mut SessionSettings) {
+    if session_settings.loop_end.is_none() {
+        println!("no loop is playing");
+    } else {
+        println!("the loop is stopped");
+    }
+    session_settings.stop_loop();
+}
+
+fn tag(arguments: &str, session_settings: &SessionSettings, paths: &[PathBuf]) {
+    let Some((tag, song)) = get_tag_arguments(arguments, session_settings, paths) else {
+        return;
+    };
+    let mut settings = playlist_settings::get_persistent_settings().get_song_settings(&song);
+    if settings.add_tag(&tag) {
+        println!("{song} is now tagged as {tag}");
+    } else {
+        println!("{song} is already tagged as {tag}");
+    }
+    playlist_settings::update_song_settings(song, settings);
+}
+
+fn untag(arguments: &str, session_settings: &SessionSettings, paths: &[PathBuf]) {
+    let Some((tag, song)) = get_tag_arguments(arguments, session_settings, paths) else {
+        return;
+    };
+    let mut settings = playlist_settings::get_persistent_settings().get_song_settings(&song);
+    if settings.remove_tag(&tag) {
+        println!("{song} is no longer tagged as {tag}");
+    } else {
+        println!("{song} is not tagged as {tag}");
+    }
+    playlist_settings::update_song_settings(song, settings);
+}
+
+//tag commands are written as "tag [tag] [index / song]", the current song is used if no song is given
+fn get_tag_arguments(
+    arguments: &str,
+    session_settings: &SessionSettings,
+    paths: &[PathBuf],
+) -> Option<(String, String)> {
+    let arguments = arguments.to_lowercase();
+    let mut arguments = arguments.split_whitespace();
+    let Some(tag) = arguments.next() else {
+        println!("this command requires a tag like \"tag workout\"");
+        return None;
+    };
+    let song = arguments.collect::<Vec<&str>>().join(" ");
+    if song.is_empty() {
+        return Some((tag.to_string(), session_settings.current_song_name.clone()));
+    }
+    let index = find_song(&song, paths)?;
+    Some((tag.to_string(), crate::get_song_name(&paths[index])))
+}
+
+fn print_tags(paths: &[PathBuf]) {
+    let settings = playlist_settings::get_persistent_settings();
+    let mut tags: Vec<(String, usize)> = Vec::new();
+    for path in paths {
+        for tag in settings.get_song_settings(&crate::get_song_name(path)).get_tags() {
+            match tags.iter_mut().find(|(other, _)| other == tag) {
+                Some((_, count)) => *count += 1,
+                None => tags.push((tag.clone(), 1)),
+            }
+        }
+    }
+    if tags.is_empty() {
+        println!("no songs are tagged");
+        return;
+    }
+    tags.sort();
+    for (tag, count) in tags {
+        println!("{tag} - {count} songs");
+    }
+}
+
+fn print_tagged_songs(tag: &str, paths: &[PathBuf]) {
+    println!("songs tagged as {tag}:");
+    let settings = playlist_settings::get_persistent_settings();
+    for (i, path) in paths.iter().enumerate() {
+        let song = crate::get_song_name(path);
+        if settings.get_song_settings(&song).has_tag(tag) {
+            println!("{i} - {song}");
+        }
+    }
+}
+
+fn turn_off_lyrics_mode(session_settings: &mut SessionSettings) {
+    session_settings.exclude_lyrics = true;
+    println!("Songs with lyrics will now be excluded from the playlist");
+}
+
+fn turn_on_lyrics_mode(session_settings: &mut SessionSettings) {
     session_settings.exclude_lyrics = false;
     println!("songs with lyrics will now be included to the playlist");
 }
@@ -490,12 +1392,24 @@ fn print_status(session_settings: &SessionSettings, paths: &[PathBuf]) {
     if !session_settings.key_events_enabled {
         println!("keyboard shortcuts are disabled");
     }
-    if !session_settings.shuffle {
+    if session_settings.shuffle {
+        println!(
+            "shuffle strategy: {} (seed: {})",
+            session_settings.shuffle_strategy.name(),
+            session_settings.get_seed()
+        );
+    } else {
         println!("playlist shuffling is disabled");
     }
     if session_settings.exclude_lyrics {
         println!("no lyrics mode is enabled");
     }
+    if let Some(filter) = &session_settings.filter {
+        println!("filter: {filter}");
+    }
+    if let Some(playlist) = &session_settings.active_playlist {
+        println!("active playlist: {playlist}");
+    }
     let song_settings = persistent_settings.get_song_settings(&session_settings.current_song_name);
     println!(
         "progress: ({})",
@@ -507,15 +1421,569 @@ fn print_status(session_settings: &SessionSettings, paths: &[PathBuf]) {
         song_settings.song_volume,
         session_settings.playback_playlist_volume()
     );
-    if song_settings.starred {
-        println!("this song is starred");
-    }
-    if song_settings.has_lyrics {
+    println!("rating: {}", playlist_settings::format_rating(song_settings.get_rating()));
+    if song_settings.has_lyrics() {
         println!("this song has lyrics");
     }
+    if !song_settings.get_tags().is_empty() {
+        println!("tags: {}", song_settings.get_tags().join(", "));
+    }
     println!("playlist index: {}", session_settings.current_song_index);
 }
 
+fn set_filter(source: &str, session_settings: &mut SessionSettings, paths: &[PathBuf]) {
+    let filter = match Filter::parse(source) {
+        Ok(filter) => filter,
+        Err(error) => {
+            println!("invalid filter: {error}");
+            println!("a filter looks like this: starred and not has_lyrics and folder = \"chill\"");
+            return;
+        }
+    };
+    let settings = playlist_settings::get_persistent_settings();
+    let matching_songs = paths
+        .iter()
+        .filter(|path| filter.matches(path, &settings))
+        .count();
+    if matching_songs == 0 {
+        println!("no song in the playlist matches this filter");
+        return;
+    }
+    println!("filter set, {matching_songs} songs match the filter");
+    session_settings.filter = Some(filter);
+}
+
+fn clear_filter(session_settings: &mut SessionSettings) {
+    if session_settings.filter.take().is_some() {
+        println!("filter removed, all songs can be played again");
+    } else {
+        println!("there is no filter set");
+    }
+}
+
+fn print_filter(session_settings: &SessionSettings, paths: &[PathBuf]) {
+    let Some(filter) = &session_settings.filter else {
+        println!("there is no filter set");
+        return;
+    };
+    println!("filter: {filter}");
+    let settings = playlist_settings::get_persistent_settings();
+    for (i, path) in paths.iter().enumerate() {
+        if filter.matches(path, &settings) {
+            println!("{i} - {}", crate::get_song_name(path));
+        }
+    }
+}
+
+fn create_playlist(name: &str) {
+    if name.is_empty() {
+        println!("this command requires a name like \"create playlist road trip\"");
+        return;
+    }
+    let mut settings = playlist_settings::get_persistent_settings();
+    if settings.create_playlist(name) {
+        playlist_settings::update_settings(&settings);
+        println!("created playlist {name}");
+    } else {
+        println!("a playlist called {name} already exists");
+    }
+}
+
+fn delete_playlist(name: &str, session_settings: &mut SessionSettings) {
+    let mut settings = playlist_settings::get_persistent_settings();
+    if !settings.remove_playlist(name) {
+        println!("there is no playlist called {name}");
+        return;
+    }
+    playlist_settings::update_settings(&settings);
+    println!("deleted playlist {name}");
+    if session_settings
+        .active_playlist
+        .as_ref()
+        .is_some_and(|playlist| playlist_settings::same_name(name, playlist))
+    {
+        session_settings.active_playlist = None;
+        println!("the whole library is played again");
+    }
+}
+
+fn play_playlist(name: &str, session_settings: &mut SessionSettings, paths: &[PathBuf]) {
+    let settings = playlist_settings::get_persistent_settings();
+    let Some((name, songs)) = settings.find_playlist(name) else {
+        println!("there is no playlist called {name}, type 'playlists' to see all playlists");
+        return;
+    };
+    let playable_songs = paths
+        .iter()
+        .filter(|path| songs.contains(&crate::get_song_name(path)))
+        .count();
+    if playable_songs == 0 {
+        println!("the playlist {name} has no songs in the library");
+        return;
+    }
+    println!("playing playlist {name} ({playable_songs} songs), the current song will finish first");
+    session_settings.active_playlist = Some(name.clone());
+    session_settings.queued_playlist = None;
+}
+
+fn leave_playlist(session_settings: &mut SessionSettings) {
+    session_settings.queued_playlist = None;
+    if session_settings.active_playlist.take().is_some() {
+        println!("the whole library is played again");
+    } else {
+        println!("no playlist is active");
+    }
+}
+
+fn print_playlists() {
+    let settings = playlist_settings::get_persistent_settings();
+    if settings.get_playlists().is_empty() {
+        println!("there are no playlists, create one with \"create playlist [name]\"");
+        return;
+    }
+    for (name, songs) in settings.get_playlists() {
+        println!("{name} - {} songs", songs.len());
+    }
+}
+
+fn print_playlist_songs(name: &str, paths: &[PathBuf]) {
+    let settings = playlist_settings::get_persistent_settings();
+    let Some((name, songs)) = settings.find_playlist(name) else {
+        println!("there is no playlist called {name}, type 'playlists' to see all playlists");
+        return;
+    };
+    println!("{name}\nPosition - Song");
+    for (i, song) in songs.iter().enumerate() {
+        let spaces = "Position".len().saturating_sub(i.to_string().len());
+        if paths.iter().any(|path| &crate::get_song_name(path) == song) {
+            println!("{}{i} - {song}", " ".repeat(spaces));
+        } else {
+            println!("{}{i} - {song} (not in the library)", " ".repeat(spaces));
+        }
+    }
+}
+
+fn merge_settings(file: &str) {
+    if file.is_empty() {
+        println!("this command requires the settings file of the other machine like \"merge settings laptop-settings.json\"");
+        return;
+    }
+    let other_settings = match playlist_settings::read_settings_file(file) {
+        Ok(other_settings) => other_settings,
+        Err(error) => {
+            println!("{error}");
+            return;
+        }
+    };
+    let mut settings = playlist_settings::get_persistent_settings();
+    match settings.merge(&other_settings) {
+        Ok(report) => {
+            playlist_settings::update_settings(&settings);
+            report.print();
+        }
+        Err(error) => println!("{error}"),
+    }
+}
+
+fn new_machine_id() {
+    let mut settings = playlist_settings::get_persistent_settings();
+    settings.set_machine_id(playlist_settings::new_machine_id());
+    let machine_id = settings.get_machine_id();
+    playlist_settings::save_machine_id(machine_id);
+    playlist_settings::update_settings(&settings);
+    println!("this machine now has the id {machine_id}, its settings can be merged with the machine they were copied from");
+}
+
+fn import_library(file: &str, paths: &[PathBuf]) {
+    if file.is_empty() {
+        println!("this command requires a file like \"import library Library.xml\" or \"import library rhythmdb.xml\"");
+        return;
+    }
+    match library_import::import_library(file, paths) {
+        Ok(report) => report.print(),
+        Err(error) => println!("{error}"),
+    }
+}
+
+fn export_settings(file: &str, paths: &[PathBuf]) {
+    if file.is_empty() {
+        println!("this command requires a file like \"export settings songs.csv\"");
+        return;
+    }
+    match settings_csv::export_settings(file, paths) {
+        Ok(songs) => println!("exported the settings of {songs} songs to {file}"),
+        Err(error) => println!("{error}"),
+    }
+}
+
+fn import_settings(file: &str, session_settings: &mut SessionSettings, paths: &[PathBuf]) {
+    if file.is_empty() {
+        println!("this command requires a file like \"import settings songs.csv\"");
+        return;
+    }
+    let settings_import = match SettingsImport::read(file, paths) {
+        Ok(settings_import) => settings_import,
+        Err(error) => {
+            println!("{error}");
+            return;
+        }
+    };
+    if !settings_import.invalid_rows.is_empty() {
+        println!("these rows are invalid and will be ignored:");
+        for row in &settings_import.invalid_rows {
+            println!("{row}");
+        }
+    }
+    if !settings_import.unknown_songs.is_empty() {
+        println!("these songs are not in the playlist folder and will be ignored:");
+        for song in &settings_import.unknown_songs {
+            println!("{song}");
+        }
+    }
+    let preview = settings_import.preview(paths);
+    if preview.is_empty() {
+        println!("{file} does not change any settings");
+        return;
+    }
+    println!("importing {file} changes:");
+    for change in &preview {
+        println!("{change}");
+    }
+    println!("type 'confirm import' to apply these {} changes or 'cancel import' to discard them", preview.len());
+    session_settings.pending_settings_import = Some(settings_import);
+}
+
+fn confirm_settings_import(session_settings: &mut SessionSettings) {
+    let Some(settings_import) = session_settings.pending_settings_import.take() else {
+        println!("there is no import waiting to be confirmed, start one with \"import settings [file]\"");
+        return;
+    };
+    let mut settings = playlist_settings::get_persistent_settings();
+    settings_import.apply(&mut settings);
+    playlist_settings::update_settings(&settings);
+    println!("imported the settings from {}", settings_import.file);
+}
+
+//written as "export playlist [file] [order / filter / starred]", the current playback order is exported by default
+fn export_playlist(arguments: &str, session_settings: &SessionSettings, paths: &[PathBuf]) {
+    let (file, source) = arguments.rsplit_once(' ').unwrap_or((arguments, ""));
+    let (file, source) = match source.to_lowercase().as_str() {
+        source @ ("order" | "filter" | "starred") => (file, source.to_string()),
+        _ => (arguments, String::from("order")),
+    };
+    let Some(format) = PlaylistFormat::from_file_name(file) else {
+        println!("this command requires a file ending with .m3u8, .m3u or .pls like \"export playlist favourites.m3u8 starred\"");
+        return;
+    };
+    let settings = playlist_settings::get_persistent_settings();
+    let songs = match source.as_str() {
+        "filter" => {
+            let Some(filter) = &session_settings.filter else {
+                println!("there is no filter set, set one with \"filter [expression]\"");
+                return;
+            };
+            paths
+                .iter()
+                .filter(|path| filter.matches(path, &settings))
+                .cloned()
+                .collect::<Vec<PathBuf>>()
+        }
+        "starred" => paths
+            .iter()
+            .filter(|path| settings.get_song_settings(&crate::get_song_name(path)).is_starred())
+            .cloned()
+            .collect(),
+        _ => get_playback_order(session_settings, paths, &settings),
+    };
+    if songs.is_empty() {
+        println!("there are no songs to export");
+        return;
+    }
+    match playlist_file::export_playlist(file, &songs, &format) {
+        Ok(()) => println!("exported {} songs to {file}", songs.len()),
+        Err(error) => println!("{error}"),
+    }
+}
+
+//the order in which songs are played without shuffling
+fn get_playback_order(
+    session_settings: &SessionSettings,
+    paths: &[PathBuf],
+    settings: &playlist_settings::PersistentSettings,
+) -> Vec<PathBuf> {
+    let songs = match session_settings.get_active_playlist(settings) {
+        Some(playlist_songs) => playlist_songs
+            .iter()
+            .filter_map(|song| paths.iter().find(|path| &crate::get_song_name(path) == song))
+            .cloned()
+            .collect::<Vec<PathBuf>>(),
+        None => paths.to_vec(),
+    };
+    songs
+        .into_iter()
+        .filter(|path| crate::is_song_selectable(path, settings, session_settings))
+        .collect()
+}
+
+//written as "import playlist [file]" or "import playlist [file] as [name]", an existing playlist is only replaced by "replace playlist"
+fn import_playlist(arguments: &str, replace: bool, paths: &[PathBuf]) {
+    let (file, name) = match arguments.rsplit_once(" as ") {
+        Some((file, name)) => (file.trim(), name.trim().to_string()),
+        None => (arguments.trim(), get_playlist_file_name(arguments.trim())),
+    };
+    if file.is_empty() || name.is_empty() {
+        println!("this command requires a file like \"import playlist road trip.m3u8\" or \"import playlist mix.pls as road trip\"");
+        return;
+    }
+    let mut settings = playlist_settings::get_persistent_settings();
+    if !replace {
+        if let Some((name, _)) = settings.find_playlist(&name) {
+            println!("there already is a playlist called {name}, type \"replace playlist {file}\" to replace it or import it with another name like \"import playlist {file} as {name} 2\"");
+            return;
+        }
+    }
+    let Some((songs, entry_count)) = read_playlist_songs(file, paths) else {
+        return;
+    };
+    let replaced = !settings.create_playlist(&name);
+    let (name, playlist_songs) = settings
+        .find_playlist_mut(&name)
+        .expect("the playlist was just created");
+    *playlist_songs = songs;
+    let name = name.clone();
+    println!(
+        "{} playlist {name} with {} of {entry_count} entries",
+        if replaced { "replaced" } else { "created" },
+        settings.find_playlist(&name).map_or(0, |(_, songs)| songs.len()),
+    );
+    playlist_settings::update_settings(&settings);
+}
+
+//a queued playlist file is only played for this session and not saved as a playlist
+fn queue_playlist(file: &str, session_settings: &mut SessionSettings, paths: &[PathBuf]) {
+    if file.is_empty() {
+        println!("this command requires a file like \"queue playlist road trip.m3u8\"");
+        return;
+    }
+    let Some((songs, entry_count)) = read_playlist_songs(file, paths) else {
+        return;
+    };
+    let name = get_playlist_file_name(file);
+    println!(
+        "playing {file} ({} of {entry_count} entries) for this session, the current song will finish first",
+        songs.len()
+    );
+    session_settings.active_playlist = Some(name.clone());
+    session_settings.queued_playlist = Some((name, songs));
+}
+
+fn get_playlist_file_name(file: &str) -> String {
+    Path::new(file)
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default()
+        .replace(['-', '_'], " ")
+}
+
+//returns the songs of the playlist file that are in the playlist folder and the number of entries of the file
+fn read_playlist_songs(file: &str, paths: &[PathBuf]) -> Option<(Vec<String>, usize)> {
+    let entries = match playlist_file::read_playlist(file) {
+        Ok(entries) => entries,
+        Err(error) => {
+            println!("{error}");
+            return None;
+        }
+    };
+    let mut songs = Vec::new();
+    let mut unresolved_entries = Vec::new();
+    for entry in &entries {
+        match playlist_file::resolve_entry(entry, paths) {
+            Some(index) => {
+                let song = crate::get_song_name(&paths[index]);
+                if !songs.contains(&song) {
+                    songs.push(song);
+                }
+            }
+            None => unresolved_entries.push(entry),
+        }
+    }
+    if !unresolved_entries.is_empty() {
+        println!("these entries are not in the playlist folder:");
+        for entry in &unresolved_entries {
+            println!("{entry}");
+        }
+    }
+    if songs.is_empty() {
+        println!("none of the {} entries of {file} could be found in the playlist folder", entries.len());
+        return None;
+    }
+    Some((songs, entries.len()))
+}
+
+//written as "add to [playlist]" for the current song or "add [index / song] to [playlist]"
+fn add_to_playlist(arguments: &str, session_settings: &SessionSettings, paths: &[PathBuf]) {
+    let arguments = arguments.to_lowercase();
+    let (song, name) = if let Some(name) = arguments.strip_prefix("to ") {
+        (session_settings.current_song_name.clone(), name)
+    } else {
+        let Some((song, name)) = arguments.rsplit_once(" to ") else {
+            println!("this command requires a playlist like \"add to road trip\" or \"add 12 to road trip\"");
+            return;
+        };
+        let Some(index) = find_song(song, paths) else {
+            return;
+        };
+        (crate::get_song_name(&paths[index]), name)
+    };
+    let mut settings = playlist_settings::get_persistent_settings();
+    let Some((name, songs)) = settings.find_playlist_mut(name) else {
+        println!("there is no playlist called {name}, create it with \"create playlist {name}\"");
+        return;
+    };
+    if songs.contains(&song) {
+        println!("{song} is already in {name}");
+        return;
+    }
+    songs.push(song.clone());
+    println!("added {song} to {name}");
+    playlist_settings::update_settings(&settings);
+}
+
+//written as "remove from [playlist]" for the current song or "remove [position / song] from [playlist]"
+fn remove_from_playlist(arguments: &str, session_settings: &SessionSettings) {
+    let arguments = arguments.to_lowercase();
+    let (song, name) = if let Some(name) = arguments.strip_prefix("from ") {
+        (session_settings.current_song_name.as_str(), name)
+    } else {
+        let Some((song, name)) = arguments.rsplit_once(" from ") else {
+            println!("this command requires a playlist like \"remove from road trip\" or \"remove 3 from road trip\"");
+            return;
+        };
+        (song, name)
+    };
+    let mut settings = playlist_settings::get_persistent_settings();
+    let Some((name, songs)) = settings.find_playlist_mut(name) else {
+        println!("there is no playlist called {name}");
+        return;
+    };
+    let position = match song.parse::<usize>() {
+        Ok(position) => (position < songs.len()).then_some(position),
+        Err(_) => songs
+            .iter()
+            .position(|other| playlist_settings::same_name(song, other)),
+    };
+    let Some(position) = position else {
+        println!("{song} is not in {name}");
+        return;
+    };
+    let song = songs.remove(position);
+    println!("removed {song} from {name}");
+    playlist_settings::update_settings(&settings);
+}
+
+//written as "move [position] to [position] in [playlist]"
+fn move_in_playlist(arguments: &str) {
+    let usage = "this command requires two positions and a playlist like \"move 3 to 0 in road trip\"";
+    let arguments = arguments.to_lowercase();
+    let Some((positions, name)) = arguments.split_once(" in ") else {
+        println!("{usage}");
+        return;
+    };
+    let Some((from, to)) = positions.split_once(" to ") else {
+        println!("{usage}");
+        return;
+    };
+    let (Ok(from), Ok(to)) = (from.trim().parse::<usize>(), to.trim().parse::<usize>()) else {
+        println!("{usage}");
+        return;
+    };
+    let mut settings = playlist_settings::get_persistent_settings();
+    let Some((name, songs)) = settings.find_playlist_mut(name) else {
+        println!("there is no playlist called {name}");
+        return;
+    };
+    if from >= songs.len() || to >= songs.len() {
+        println!("the playlist {name} only has positions 0 to {}", songs.len().saturating_sub(1));
+        return;
+    }
+    let song = songs.remove(from);
+    println!("moved {song} to position {to} in {name}");
+    songs.insert(to, song);
+    playlist_settings::update_settings(&settings);
+}
+
+fn save_smart_playlist(name: &str, session_settings: &mut SessionSettings) {
+    if name.is_empty() {
+        println!("this command requires a name like \"save filter as Focus favourites\"");
+        return;
+    }
+    let Some(filter) = session_settings.filter.take() else {
+        println!("there is no filter set, set one with \"filter [expression]\" first");
+        return;
+    };
+    let mut settings = playlist_settings::get_persistent_settings();
+    settings.set_smart_playlist(name, filter.get_source());
+    playlist_settings::update_settings(&settings);
+    println!("saved smart playlist {name}: {}", filter.get_source());
+    session_settings.filter = Some(filter.named(name));
+}
+
+fn delete_smart_playlist(name: &str) {
+    let mut settings = playlist_settings::get_persistent_settings();
+    if settings.remove_smart_playlist(name) {
+        playlist_settings::update_settings(&settings);
+        println!("deleted smart playlist {name}");
+    } else {
+        println!("there is no smart playlist called {name}");
+    }
+}
+
+fn play_smart_playlist(name: &str, session_settings: &mut SessionSettings, paths: &[PathBuf]) {
+    let Some((name, source)) = playlist_settings::get_persistent_settings().find_smart_playlist(name)
+    else {
+        println!("there is no smart playlist called {name}, type 'smart playlists' to see all smart playlists");
+        return;
+    };
+    let filter = match Filter::parse(&source) {
+        Ok(filter) => filter.named(&name),
+        Err(error) => {
+            println!("the smart playlist {name} has an invalid filter: {error}");
+            return;
+        }
+    };
+    let settings = playlist_settings::get_persistent_settings();
+    let matching_songs = paths
+        .iter()
+        .filter(|path| filter.matches(path, &settings))
+        .count();
+    if matching_songs == 0 {
+        println!("no song in the playlist currently belongs to {name}");
+        return;
+    }
+    println!("playing smart playlist {name} ({matching_songs} songs)");
+    session_settings.filter = Some(filter);
+}
+
+fn print_smart_playlists(paths: &[PathBuf]) {
+    let settings = playlist_settings::get_persistent_settings();
+    if settings.get_smart_playlists().is_empty() {
+        println!("there are no smart playlists, save one with \"save filter as [name]\"");
+        return;
+    }
+    for (name, source) in settings.get_smart_playlists() {
+        match Filter::parse(source) {
+            Ok(filter) => {
+                let matching_songs = paths
+                    .iter()
+                    .filter(|path| filter.matches(path, &settings))
+                    .count();
+                println!("{name} - {source} ({matching_songs} songs)");
+            }
+            Err(error) => println!("{name} - {source} (invalid: {error})"),
+        }
+    }
+}
+
 fn print_index(session_settings: &SessionSettings) {
     println!("playlist index: {}", session_settings.current_song_index);
 }
@@ -536,25 +2004,22 @@ fn print_progress(session_settings: &SessionSettings) {
     }
 }
 
-fn print_song_probabilities(paths: &[PathBuf]) {
+fn print_song_probabilities(session_settings: &SessionSettings, paths: &[PathBuf]) {
+    if session_settings.shuffle_strategy.name() != "weighted" {
+        println!("these probabilities only apply to the weighted shuffle strategy");
+    }
     let settings = playlist_settings::get_persistent_settings();
-    let mut probabilities = Vec::new();
-    let mut sum = 0;
-    for i in 0..paths.len() {
-        let song_settings = settings.get_song_settings(&crate::get_song_name(&paths[i]));
-        let base_probability = settings.get_probability_distribution(paths)[i];
-        let p = base_probability;
-        let star_factor = if song_settings.starred { 2 } else { 1 };
-        sum += p * star_factor;
-        probabilities.push((p * star_factor, base_probability));
-    }
-    let message = probabilities
+    println!("probability growth: {}", settings.probability_growth);
+    let weights = shuffle_strategy::get_song_weights(paths, &settings);
+    let sum = weights.iter().sum::<f64>();
+    let message = weights
         .into_iter()
+        .zip(settings.get_probability_distribution(paths))
         .enumerate()
         .map(|(i, p)| {
             (
                 crate::get_song_name(&paths[i]),
-                p.0 as f32 * 100.0 / sum as f32,
+                p.0 * 100.0 / sum,
                 p.1,
             )
         })
@@ -564,14 +2029,34 @@ fn print_song_probabilities(paths: &[PathBuf]) {
     println!("{message}");
 }
 
+fn check_library(paths: &[PathBuf]) {
+    println!("checking library, every song is decoded so this can take a while...");
+    let report = library::check_library(paths);
+    report.print();
+    if !report.relink_candidates.is_empty() {
+        println!("type 'relink library' to move the settings of renamed songs to their new name");
+    }
+    if !report.orphaned_entries.is_empty() {
+        println!("type 'prune library' to remove all settings entries of songs that no longer exist");
+    }
+}
+
 fn print_commands() {
     let file_path = "commands.txt";
     let contents = fs::read_to_string(file_path).expect("Failed to read the file");
     println!("{contents}");
 }
 
-fn exit_program(crash_reporter: &mut CrashReporter) {
+fn exit_program(
+    audio_player: &Sink,
+    session_settings: &SessionSettings,
+    paths: &[PathBuf],
+    crash_reporter: &mut CrashReporter,
+) {
     println!("closing audio player");
+    interrupt_listen(audio_player, session_settings);
+    let position = session_settings.song_position(audio_player.is_paused());
+    LastSession::new(session_settings, position, paths).save();
     crash_reporter.disable();
     process::exit(0);
 }
@@ -627,25 +2112,27 @@ fn choose_song(
     paths: &[PathBuf],
     session_settings: &mut SessionSettings,
 ) {
-    match new_song.parse::<usize>() {
-        Ok(index) => {
-            if index >= paths.len() {
-                println!("the given index does not exist in the playlist");
-                return;
-            }
-            choose_song_by_index(audio_player, paths, index, session_settings);
-        }
-        Err(_) => match paths.iter().position(|song| crate::get_song_name(song).replace(' ', "") == new_song) {
-            Some(index) => {
-                audio_player.clear();
-                let (source, song) = crate::index_song(paths, index);
-                audio_player.append(source);
-                audio_player.play();
-                println!("Now playing {song}");
-            }
-            None => println!("this command requires a positive integer as an index or the name of a song in the playlist")
+    if let Some(index) = find_song(new_song, paths) {
+        choose_song_by_index(audio_player, paths, index, session_settings);
+    }
+}
+
+fn find_song(song: &str, paths: &[PathBuf]) -> Option<usize> {
+    if let Ok(index) = song.trim().parse::<usize>() {
+        if index >= paths.len() {
+            println!("the given index does not exist in the playlist");
+            return None;
         }
+        return Some(index);
     }
+    let song = song.replace(' ', "").to_lowercase();
+    let index = paths
+        .iter()
+        .position(|path| crate::get_song_name(path).replace(' ', "").to_lowercase() == song);
+    if index.is_none() {
+        println!("this command requires a positive integer as an index or the name of a song in the playlist");
+    }
+    index
 }
 
 fn choose_song_by_index(
@@ -654,8 +2141,15 @@ fn choose_song_by_index(
     index: usize,
     session_settings: &mut SessionSettings,
 ) {
+    let (mut source, file_name) = match crate::index_song(paths, index) {
+        Ok(song) => song,
+        Err(error) => {
+            crate::mark_song_as_broken(&paths[index], &error);
+            return;
+        }
+    };
+    record_skip(audio_player, session_settings);
     audio_player.clear();
-    let (source, file_name) = crate::index_song(paths, index);
     session_settings.song_duration = source
         .total_duration();
     println!(
@@ -663,6 +2157,10 @@ fn choose_song_by_index(
         session_settings.format_song_duration(),
     );
     let mut settings = playlist_settings::get_persistent_settings();
+    session_settings.current_song_name.clone_from(&file_name);
+    session_settings.reset_song_progress();
+    session_settings.stop_loop();
+    crate::resume_song_position(&mut source, &settings, session_settings);
     let song_settings = settings.get_song_settings(&file_name);
     let song_volume = song_settings.song_volume;
     let playlist_volume = settings.volume;
@@ -673,27 +2171,26 @@ fn choose_song_by_index(
             (song_volume * playlist_volume * 100.0).round()
         );
     }
-    if song_settings.starred {
-        println!("This song is starred");
+    if let Some(rating) = song_settings.rating {
+        println!("Rating: {}", playlist_settings::format_rating(rating));
     }
     audio_player.append(source);
     let song_settings = settings.get_song_settings(&file_name);
     let track_volume = song_settings.song_volume * settings.volume;
     audio_player.set_volume(track_volume);
     audio_player.play();
+    settings.record_play(&file_name, Local::now().timestamp());
+    session_settings.shuffle_strategy.song_played(&file_name);
     session_settings.current_song_index = index;
     session_settings.current_song_name = file_name;
     session_settings.duration_start = Instant::now();
+    session_settings.song_started = Local::now().timestamp();
     session_settings.after_song = AfterSong::Continue;
     if session_settings.shuffle {
         let mut choosable_songs = 0;
         for i in 0..paths.len() {
             settings.set_song_probability(paths[i].to_str().expect("path has no name"), settings.get_probability_distribution(paths)[i] + 1);
-            if !session_settings.exclude_lyrics
-                || !settings
-                    .get_song_settings(&crate::get_song_name(&paths[i]))
-                    .has_lyrics
-            {
+            if crate::is_song_selectable(&paths[i], &settings, session_settings) {
                 choosable_songs += 1;
             }
         }
@@ -717,9 +2214,30 @@ fn switch_lyrics_mode(session_settings: &mut SessionSettings) {
 }
 
 fn restart_song(audio_player: &Sink, paths: &[PathBuf], session_settings: &mut SessionSettings) {
+    let index = session_settings.current_song_index;
+    if paths.get(index).map(|path| crate::get_song_name(path)).as_ref()
+        != Some(&session_settings.current_song_name)
+    {
+        println!("the current song is no longer in the playlist");
+        return;
+    }
+    let (source, song_name) = match crate::index_song(paths, index) {
+        Ok(song) => song,
+        Err(error) => {
+            crate::mark_song_as_broken(&paths[index], &error);
+            return;
+        }
+    };
+    interrupt_listen(audio_player, session_settings);
+    //a restarted song also starts from the beginning the next time it is played
+    let mut settings = playlist_settings::get_persistent_settings();
+    settings.set_song_position(&song_name, None);
+    playlist_settings::update_settings(&settings);
     audio_player.clear();
-    let (source, song_name) = crate::index_song(paths, session_settings.current_song_index);
     audio_player.append(source);
+    session_settings.duration_start = Instant::now();
+    session_settings.song_started = Local::now().timestamp();
+    session_settings.reset_song_progress();
     let song_settings = playlist_settings::get_persistent_settings().get_song_settings(&song_name);
     audio_player
         .set_volume(session_settings.playback_playlist_volume() * song_settings.song_volume);
@@ -728,19 +2246,119 @@ fn restart_song(audio_player: &Sink, paths: &[PathBuf], session_settings: &mut S
     session_settings.current_song_name = song_name;
 }
 
+fn trim_start(audio_player: &Sink, paths: &[PathBuf], session_settings: &mut SessionSettings) {
+    if audio_player.empty() {
+        println!("no song is playing");
+        return;
+    }
+    let song = session_settings.current_song_name.clone();
+    let mut settings = playlist_settings::get_persistent_settings();
+    let mut song_settings = settings.get_song_settings(&song);
+    let old_start = song_settings.get_trim_start();
+    let start = old_start + session_settings.song_position(audio_player.is_paused());
+    if song_settings.get_trim_end().is_some_and(|end| start >= end) {
+        println!("the start of the song has to be before its end");
+        return;
+    }
+    song_settings.trim_start = Some(start.as_secs()).filter(|start| *start > 0);
+    settings.set_song_settings(song.clone(), song_settings);
+    settings.move_song_start(&song, old_start, start);
+    playlist_settings::update_settings(&settings);
+    println!("{song} now starts at {}", utils::format_duration(&start));
+    reload_song(audio_player, paths, session_settings, Duration::ZERO);
+}
+
+fn trim_end(audio_player: &Sink, paths: &[PathBuf], session_settings: &mut SessionSettings) {
+    if audio_player.empty() {
+        println!("no song is playing");
+        return;
+    }
+    let song = session_settings.current_song_name.clone();
+    let position = session_settings.song_position(audio_player.is_paused());
+    if position.as_secs() == 0 {
+        println!("the end of the song has to be after its start");
+        return;
+    }
+    let mut song_settings = playlist_settings::get_persistent_settings().get_song_settings(&song);
+    let end = song_settings.get_trim_start() + position;
+    song_settings.trim_end = Some(end.as_secs());
+    playlist_settings::update_song_settings(song.clone(), song_settings);
+    println!("{song} now ends at {}", utils::format_duration(&end));
+    //the reloaded song ends right away, so the next song starts as if it had finished
+    reload_song(audio_player, paths, session_settings, position);
+}
+
+fn reset_trim(audio_player: &Sink, paths: &[PathBuf], session_settings: &mut SessionSettings) {
+    let song = session_settings.current_song_name.clone();
+    let mut settings = playlist_settings::get_persistent_settings();
+    let mut song_settings = settings.get_song_settings(&song);
+    if song_settings.trim_start.is_none() && song_settings.trim_end.is_none() {
+        println!("{song} is not trimmed");
+        return;
+    }
+    let old_start = song_settings.get_trim_start();
+    song_settings.trim_start = None;
+    song_settings.trim_end = None;
+    settings.set_song_settings(song.clone(), song_settings);
+    settings.move_song_start(&song, old_start, Duration::ZERO);
+    playlist_settings::update_settings(&settings);
+    println!("{song} is played in full length again");
+    if !audio_player.empty() {
+        let position = old_start + session_settings.song_position(audio_player.is_paused());
+        reload_song(audio_player, paths, session_settings, position);
+    }
+}
+
+//decodes the current song again after its trim points changed and continues at the given position
+fn reload_song(
+    audio_player: &Sink,
+    paths: &[PathBuf],
+    session_settings: &mut SessionSettings,
+    position: Duration,
+) {
+    let index = session_settings.current_song_index;
+    if paths.get(index).map(|path| crate::get_song_name(path)).as_ref()
+        != Some(&session_settings.current_song_name)
+    {
+        println!("the current song is no longer in the playlist");
+        return;
+    }
+    let (mut source, song_name) = match crate::index_song(paths, index) {
+        Ok(song) => song,
+        Err(error) => {
+            crate::mark_song_as_broken(&paths[index], &error);
+            return;
+        }
+    };
+    if let Err(error) = source.try_seek(position) {
+        println!("Failed to jump to {}: {error}", utils::format_duration(&position));
+        return;
+    }
+    let is_paused = audio_player.is_paused();
+    session_settings.song_duration = source.total_duration();
+    session_settings.seek_song_progress(position, is_paused);
+    session_settings.stop_loop();
+    audio_player.clear();
+    audio_player.append(source);
+    let song_settings = playlist_settings::get_persistent_settings().get_song_settings(&song_name);
+    audio_player
+        .set_volume(session_settings.playback_playlist_volume() * song_settings.song_volume);
+    if !is_paused {
+        audio_player.play();
+    }
+    println!("{song_name} is now {} long", session_settings.format_song_duration());
+}
+
 fn next_song(
     audio_player: &Sink,
     paths: &[PathBuf],
     session_settings: &mut SessionSettings,
     crash_reporter: &mut CrashReporter,
 ) {
+    record_skip(audio_player, session_settings);
     audio_player.clear();
-    let index = crate::get_next_song_index(session_settings, paths);
-    crash_reporter.next_song(
-        crate::get_song_name(&paths[index]),
-        session_settings.clone(),
-    );
-    let (source, _, song_name) = crate::play_next_song(index, paths, session_settings);
+    let (source, _, song_name) =
+        crate::play_next_playable_song(paths, session_settings, crash_reporter);
     audio_player.append(source);
     let song_settings = playlist_settings::get_persistent_settings().get_song_settings(&song_name);
     audio_player
@@ -809,20 +2427,8 @@ fn pause_after_song(session_settings: &mut SessionSettings) {
 }
 
 fn choose_next_song(session_settings: &mut SessionSettings, next_song: &str, paths: &[PathBuf]) {
-    match next_song.parse::<usize>() {
-        Ok(index) => {
-            if index >= paths.len() {
-                println!("the given index does not exist in the playlist");
-                return;
-            }
-            session_settings.after_song = AfterSong::PlaySong(index);
-        }
-        Err(_) => match paths.iter().position(|song| crate::get_song_name(song).replace(' ', "") == next_song) {
-            Some(index) => {
-                session_settings.after_song = AfterSong::PlaySong(index);
-            }
-            None => println!("this command requires a positive integer as an index or the name of a song in the playlist")
-        }
+    if let Some(index) = find_song(next_song, paths) {
+        session_settings.after_song = AfterSong::PlaySong(index);
     }
 }
 
@@ -831,6 +2437,17 @@ fn continue_after_song(session_settings: &mut SessionSettings) {
 }
 
 fn print_play_count(song: &str) {
-    let play_count = playlist_settings::get_persistent_settings().get_song_play_count(song);
+    let settings = playlist_settings::get_persistent_settings();
+    let play_count = settings.get_song_play_count(song);
     println!("{song} has been played {play_count} times");
+    let listen_time = settings.get_listen_time(song);
+    if listen_time.listens() > 0 {
+        println!(
+            "listened to {} in full ({} times) and {} partially ({} times)",
+            utils::format_duration(&Duration::from_secs(listen_time.full_seconds)),
+            listen_time.full_listens,
+            utils::format_duration(&Duration::from_secs(listen_time.partial_seconds)),
+            listen_time.partial_listens
+        );
+    }
 }
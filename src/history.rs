@@ -0,0 +1,132 @@
+use std::{
+    fmt::Display,
+    fs::{self, OpenOptions},
+    io::Write,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::playlist_settings::{PlayThreshold, SessionSettings};
+
+const HISTORY_FILE: &str = "listening-history.jsonl";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListenEnd {
+    Completed,
+    Skipped,
+    //the player was closed or the song was restarted
+    Interrupted,
+}
+
+impl Display for ListenEnd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ListenEnd::Completed => write!(f, "completed"),
+            ListenEnd::Skipped => write!(f, "skipped"),
+            ListenEnd::Interrupted => write!(f, "interrupted"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Listen {
+    pub song: String,
+    pub started: i64,
+    pub listened_seconds: u64,
+    pub end: ListenEnd,
+    pub shuffle: bool,
+    pub exclude_lyrics: bool,
+    pub muted: bool,
+    #[serde(default)]
+    pub shuffle_strategy: String,
+    #[serde(default)]
+    pub filter: Option<String>,
+    #[serde(default)]
+    pub playlist: Option<String>,
+    #[serde(default)]
+    pub song_seconds: Option<u64>,
+}
+
+impl Listen {
+    pub fn new(
+        session_settings: &SessionSettings,
+        listened_seconds: u64,
+        end: ListenEnd,
+    ) -> Self {
+        Self {
+            song: session_settings.current_song_name.clone(),
+            started: session_settings.song_started,
+            listened_seconds,
+            end,
+            shuffle: session_settings.shuffle,
+            exclude_lyrics: session_settings.exclude_lyrics,
+            muted: session_settings.is_muted,
+            shuffle_strategy: session_settings.shuffle_strategy.name().to_string(),
+            filter: session_settings
+                .filter
+                .as_ref()
+                .map(|filter| filter.get_source().to_string()),
+            playlist: session_settings.active_playlist.clone(),
+            song_seconds: session_settings.song_duration.map(|duration| duration.as_secs()),
+        }
+    }
+
+    //listens from before the song length was recorded can only reach the minutes threshold
+    pub fn is_play(&self, play_threshold: PlayThreshold) -> bool {
+        self.end == ListenEnd::Completed
+            || play_threshold.is_reached(
+                Duration::from_secs(self.listened_seconds),
+                self.song_seconds.map(Duration::from_secs),
+            )
+    }
+
+    pub fn get_modes(&self) -> Vec<String> {
+        let mut modes = Vec::new();
+        if self.shuffle {
+            modes.push(format!("shuffle ({})", self.shuffle_strategy));
+        }
+        if self.exclude_lyrics {
+            modes.push(String::from("no lyrics"));
+        }
+        if self.muted {
+            modes.push(String::from("muted"));
+        }
+        if let Some(filter) = &self.filter {
+            modes.push(format!("filter: {filter}"));
+        }
+        if let Some(playlist) = &self.playlist {
+            modes.push(format!("playlist: {playlist}"));
+        }
+        modes
+    }
+}
+
+//every listen is appended as its own line so the history never has to be rewritten
+pub fn append_listen(listen: &Listen) {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(HISTORY_FILE)
+        .expect("Failed to open the listening history");
+    let json = serde_json::to_string(listen).expect("json conversion failed");
+    writeln!(file, "{json}").expect("Failed to write to the listening history");
+}
+
+pub fn get_history() -> Vec<Listen> {
+    let Ok(history) = fs::read_to_string(HISTORY_FILE) else {
+        return Vec::new();
+    };
+    //a line that was only partly written when the player was closed is ignored
+    history
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+pub fn get_history_since(timestamp: i64) -> Vec<Listen> {
+    get_history()
+        .into_iter()
+        .filter(|listen| listen.started >= timestamp)
+        .collect()
+}
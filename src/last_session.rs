@@ -0,0 +1,115 @@
+use std::{fs, path::PathBuf, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    filter::Filter,
+    playlist_settings::{AfterSong, SessionSettings},
+    shuffle_strategy, utils,
+};
+
+const LAST_SESSION_FILE: &str = "last-session.json";
+
+//songs are stored by name because their indices change when songs are added or removed
+#[derive(Serialize, Deserialize)]
+pub struct LastSession {
+    song: String,
+    position_seconds: u64,
+    next_song: Option<String>,
+    pause_after_song: bool,
+    shuffle: bool,
+    exclude_lyrics: bool,
+    is_muted: bool,
+    key_events_enabled: bool,
+    shuffle_strategy: String,
+    filter: Option<String>,
+    filter_name: Option<String>,
+    active_playlist: Option<String>,
+}
+
+impl LastSession {
+    pub fn new(session_settings: &SessionSettings, position: Duration, paths: &[PathBuf]) -> Self {
+        let next_song = match session_settings.after_song {
+            AfterSong::PlaySong(index) => paths.get(index).map(|path| crate::get_song_name(path)),
+            _ => None,
+        };
+        Self {
+            song: session_settings.current_song_name.clone(),
+            position_seconds: position.as_secs(),
+            next_song,
+            pause_after_song: matches!(session_settings.after_song, AfterSong::Pause),
+            shuffle: session_settings.shuffle,
+            exclude_lyrics: session_settings.exclude_lyrics,
+            is_muted: session_settings.is_muted,
+            key_events_enabled: session_settings.key_events_enabled,
+            shuffle_strategy: session_settings.shuffle_strategy.name().to_string(),
+            filter: session_settings
+                .filter
+                .as_ref()
+                .map(|filter| filter.get_source().to_string()),
+            filter_name: session_settings
+                .filter
+                .as_ref()
+                .and_then(|filter| filter.get_name().map(String::from)),
+            active_playlist: session_settings.active_playlist.clone(),
+        }
+    }
+
+    pub fn save(&self) {
+        utils::write_to_file(
+            LAST_SESSION_FILE,
+            &serde_json::to_string(self).expect("json conversion failed"),
+        );
+    }
+
+    pub fn load() -> Option<Self> {
+        let json = fs::read_to_string(LAST_SESSION_FILE).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    pub fn get_position(&self) -> Duration {
+        Duration::from_secs(self.position_seconds)
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} at {}",
+            self.song,
+            utils::format_duration(&self.get_position())
+        )
+    }
+
+    //returns the index of the song to resume if it is still in the playlist
+    pub fn restore(&self, session_settings: &mut SessionSettings, paths: &[PathBuf]) -> Option<usize> {
+        session_settings.shuffle = self.shuffle;
+        session_settings.exclude_lyrics = self.exclude_lyrics;
+        session_settings.is_muted = self.is_muted;
+        session_settings.key_events_enabled = self.key_events_enabled;
+        if let Some(strategy) = shuffle_strategy::from_name(&self.shuffle_strategy) {
+            session_settings.shuffle_strategy = strategy;
+        }
+        if let Some(source) = &self.filter {
+            match Filter::parse(source) {
+                Ok(filter) => {
+                    session_settings.filter = Some(match &self.filter_name {
+                        Some(name) => filter.named(name),
+                        None => filter,
+                    });
+                }
+                Err(error) => println!("the filter of the last session is no longer valid: {error}"),
+            }
+        }
+        session_settings.active_playlist = self.active_playlist.clone();
+        let find_song = |song: &str| paths.iter().position(|path| crate::get_song_name(path) == song);
+        session_settings.after_song = match &self.next_song {
+            Some(next_song) => find_song(next_song).map_or(AfterSong::Continue, AfterSong::PlaySong),
+            None if self.pause_after_song => AfterSong::Pause,
+            None => AfterSong::Continue,
+        };
+        let index = find_song(&self.song);
+        if index.is_none() {
+            println!("{} is no longer in the playlist, the last session is resumed with another song", self.song);
+        }
+        index
+    }
+}
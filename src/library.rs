@@ -0,0 +1,352 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use rodio::Source;
+
+use crate::{
+    playlist_settings::{self, AfterSong, SessionSettings},
+    song_error::SongError,
+    utils,
+};
+
+pub struct LibraryReport {
+    pub undecodable_songs: Vec<(PathBuf, SongError)>,
+    pub songs_without_duration: Vec<PathBuf>,
+    pub repaired_songs: Vec<PathBuf>,
+    pub orphaned_entries: Vec<String>,
+    pub relink_candidates: Vec<(String, String)>,
+    pub name_collisions: Vec<(String, Vec<PathBuf>)>,
+}
+
+impl LibraryReport {
+    pub fn is_healthy(&self) -> bool {
+        self.undecodable_songs.is_empty()
+            && self.songs_without_duration.is_empty()
+            && self.orphaned_entries.is_empty()
+            && self.name_collisions.is_empty()
+    }
+
+    pub fn print(&self) {
+        for path in &self.repaired_songs {
+            println!("{} is playable again and no longer marked as broken", path.display());
+        }
+        if self.is_healthy() {
+            println!("no problems found in the library");
+            return;
+        }
+        if !self.undecodable_songs.is_empty() {
+            println!("songs that failed to decode (these are marked as broken and will be skipped):");
+            for (path, error) in &self.undecodable_songs {
+                println!("{} - {error}", path.display());
+            }
+        }
+        if !self.songs_without_duration.is_empty() {
+            println!("songs without a known duration:");
+            for path in &self.songs_without_duration {
+                println!("{}", path.display());
+            }
+        }
+        if !self.orphaned_entries.is_empty() {
+            println!("settings entries for songs that no longer exist:");
+            for song in &self.orphaned_entries {
+                match self.relink_candidates.iter().find(|(old, _)| old == song) {
+                    Some((_, new)) => println!("{song} (probably renamed to {new})"),
+                    None => println!("{song}"),
+                }
+            }
+        }
+        if !self.name_collisions.is_empty() {
+            println!("files that share the same song name:");
+            for (song, paths) in &self.name_collisions {
+                let paths = paths
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                println!("{song} - {paths}");
+            }
+        }
+    }
+}
+
+//songs that end a little early are still fine, the duration of some files is only estimated
+const CORRUPT_SONG_TOLERANCE: Duration = Duration::from_secs(2);
+
+//the header of a file can be valid while its frames are corrupt, so every sample is decoded
+fn decode_all_samples(path: &Path) -> Result<Option<Duration>, SongError> {
+    let source = crate::decode_song(path)?;
+    let total_duration = source.total_duration();
+    let samples_per_second = (u64::from(source.sample_rate()) * u64::from(source.channels())).max(1);
+    let samples = u64::try_from(source.count()).unwrap_or(u64::MAX);
+    let decoded = Duration::from_millis(samples * 1000 / samples_per_second);
+    match total_duration {
+        Some(expected) if decoded + CORRUPT_SONG_TOLERANCE < expected => Err(SongError::Corrupt {
+            decoded,
+            expected: Some(expected),
+        }),
+        None if samples == 0 => Err(SongError::Corrupt {
+            decoded,
+            expected: None,
+        }),
+        _ => Ok(total_duration),
+    }
+}
+
+pub fn check_library(paths: &[PathBuf]) -> LibraryReport {
+    let mut undecodable_songs = Vec::new();
+    let mut songs_without_duration = Vec::new();
+    let mut repaired_songs = Vec::new();
+    let mut settings = playlist_settings::get_persistent_settings();
+    for path in paths {
+        let song = get_display_name(path);
+        let mut song_settings = song
+            .as_ref()
+            .map(|song| settings.get_song_settings(song))
+            .unwrap_or_default();
+        let was_broken = song_settings.is_broken;
+        match decode_all_samples(path) {
+            Ok(total_duration) => {
+                if total_duration.is_none() {
+                    songs_without_duration.push(path.clone());
+                }
+                if was_broken {
+                    repaired_songs.push(path.clone());
+                }
+                song_settings.is_broken = false;
+            }
+            Err(error) => {
+                undecodable_songs.push((path.clone(), error));
+                song_settings.is_broken = true;
+            }
+        }
+        if let Some(song) = song {
+            if song_settings.is_broken != was_broken {
+                settings.set_song_settings(song, song_settings);
+            }
+        }
+    }
+    playlist_settings::update_settings(&settings);
+
+    let mut name_collisions: Vec<(String, Vec<PathBuf>)> = Vec::new();
+    for path in paths {
+        let Some(song) = get_display_name(path) else {
+            continue;
+        };
+        match name_collisions.iter_mut().find(|(other, _)| &song == other) {
+            Some((_, colliding_paths)) => colliding_paths.push(path.clone()),
+            None => name_collisions.push((song, vec![path.clone()])),
+        }
+    }
+    name_collisions.retain(|(_, colliding_paths)| colliding_paths.len() > 1);
+
+    let (orphaned_entries, relink_candidates) = find_orphaned_entries(paths);
+
+    LibraryReport {
+        undecodable_songs,
+        songs_without_duration,
+        repaired_songs,
+        orphaned_entries,
+        relink_candidates,
+        name_collisions,
+    }
+}
+
+pub fn prune_orphaned_entries(paths: &[PathBuf]) {
+    let (orphaned_entries, _) = find_orphaned_entries(paths);
+    let mut settings = playlist_settings::get_persistent_settings();
+    for song in &orphaned_entries {
+        settings.remove_song(song);
+    }
+    playlist_settings::update_settings(&settings);
+    println!("removed {} settings entries", orphaned_entries.len());
+}
+
+pub fn relink_orphaned_entries(paths: &[PathBuf]) {
+    let (_, relink_candidates) = find_orphaned_entries(paths);
+    let mut settings = playlist_settings::get_persistent_settings();
+    for (old_song, new_song) in &relink_candidates {
+        settings.rename_song(old_song, new_song);
+        println!("{old_song} -> {new_song}");
+    }
+    playlist_settings::update_settings(&settings);
+    println!("re-linked {} settings entries", relink_candidates.len());
+}
+
+pub fn check_library_on_startup(paths: &[PathBuf]) {
+    println!("checking library, every song is decoded so this can take a while...");
+    let report = check_library(paths);
+    report.print();
+    if report.orphaned_entries.is_empty() {
+        return;
+    }
+    println!("type 'relink' to move settings to renamed songs, 'prune' to remove all orphaned entries or press enter to continue");
+    match utils::get_console_input().trim().to_lowercase().as_str() {
+        "relink" => relink_orphaned_entries(paths),
+        "prune" => prune_orphaned_entries(paths),
+        _ => (),
+    }
+}
+
+pub fn watch_playlist_directory(
+    library_events: Arc<Mutex<Vec<notify::Event>>>,
+) -> Option<RecommendedWatcher> {
+    let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            library_events.lock().unwrap().push(event);
+        }
+    });
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(error) => {
+            println!("Failed to watch the playlist directory, new songs will only be found after a restart ({error})");
+            return None;
+        }
+    };
+    if let Err(error) = watcher.watch(Path::new("playlist"), RecursiveMode::Recursive) {
+        println!("Failed to watch the playlist directory, new songs will only be found after a restart ({error})");
+        return None;
+    }
+    Some(watcher)
+}
+
+pub fn update_library(
+    paths: &mut Vec<PathBuf>,
+    session_settings: &mut SessionSettings,
+    changed_paths: &[PathBuf],
+) {
+    let new_paths = crate::get_song_paths();
+
+    //removed songs are taken out without reordering the rest of the playlist, so indices only shift down
+    let mut index = 0;
+    while index < paths.len() {
+        if new_paths.contains(&paths[index]) {
+            index += 1;
+            continue;
+        }
+        let removed_path = paths.remove(index);
+        let song = crate::get_song_name(&removed_path);
+        println!("{song} was removed from the playlist");
+        if session_settings.current_song_index == index {
+            println!("the current song will finish playing but can no longer be restarted");
+            session_settings.current_song_index = index
+                .checked_sub(1)
+                .unwrap_or(paths.len().saturating_sub(1));
+        } else if session_settings.current_song_index > index {
+            session_settings.current_song_index -= 1;
+        }
+        match session_settings.after_song {
+            AfterSong::PlaySong(next_song) if next_song == index => {
+                println!("{song} will no longer be played after the current song");
+                session_settings.after_song = AfterSong::Continue;
+            }
+            AfterSong::PlaySong(next_song) if next_song > index => {
+                session_settings.after_song = AfterSong::PlaySong(next_song - 1);
+            }
+            _ => (),
+        }
+    }
+
+    //new songs are appended so that the indices of existing songs stay the same
+    for path in new_paths {
+        if !paths.contains(&path) {
+            println!(
+                "{} was added to the playlist at index {}",
+                crate::get_song_name(&path),
+                paths.len()
+            );
+            paths.push(path);
+        }
+    }
+
+    //a changed file might have been repaired, so it is tried again
+    //notify reports absolute paths while the playlist paths are relative, so both are compared canonicalized
+    let changed_paths = changed_paths
+        .iter()
+        .filter_map(|path| fs::canonicalize(path).ok())
+        .collect::<Vec<PathBuf>>();
+    let mut settings = playlist_settings::get_persistent_settings();
+    let mut settings_changed = false;
+    for path in paths.iter() {
+        if !fs::canonicalize(path).is_ok_and(|path| changed_paths.contains(&path)) {
+            continue;
+        }
+        let song = crate::get_song_name(path);
+        let mut song_settings = settings.get_song_settings(&song);
+        if song_settings.is_broken {
+            println!("{song} has changed and is no longer marked as broken");
+            song_settings.is_broken = false;
+            settings.set_song_settings(song, song_settings);
+            settings_changed = true;
+        }
+    }
+    if settings_changed {
+        playlist_settings::update_settings(&settings);
+    }
+}
+
+fn find_orphaned_entries(paths: &[PathBuf]) -> (Vec<String>, Vec<(String, String)>) {
+    let settings = playlist_settings::get_persistent_settings();
+    let keys = settings.get_song_keys();
+    let song_names = paths
+        .iter()
+        .filter_map(|path| get_display_name(path))
+        .collect::<Vec<String>>();
+    let path_names = paths
+        .iter()
+        .filter_map(|path| path.to_str().map(String::from))
+        .collect::<Vec<String>>();
+
+    let orphaned_entries = keys
+        .iter()
+        .filter(|key| !song_names.contains(key) && !path_names.contains(key))
+        .cloned()
+        .collect::<Vec<String>>();
+
+    let mut relink_candidates = Vec::new();
+    for old_song in &orphaned_entries {
+        //entries are keyed either by song name or by path, a re-linked entry keeps its kind of key
+        let is_path = Path::new(old_song).components().count() > 1;
+        let old_name = if is_path {
+            get_display_name(Path::new(old_song)).unwrap_or_else(|| old_song.clone())
+        } else {
+            old_song.clone()
+        };
+        let candidates = paths
+            .iter()
+            .filter(|path| {
+                get_display_name(path).is_some_and(|song| normalize(&song) == normalize(&old_name))
+            })
+            .collect::<Vec<&PathBuf>>();
+        let [path] = candidates.as_slice() else {
+            continue;
+        };
+        let new_song = if is_path {
+            path.to_str().map(String::from)
+        } else {
+            get_display_name(path)
+        };
+        if let Some(new_song) = new_song {
+            if !keys.contains(&new_song) {
+                relink_candidates.push((old_song.clone(), new_song));
+            }
+        }
+    }
+    (orphaned_entries, relink_candidates)
+}
+
+fn get_display_name(path: &Path) -> Option<String> {
+    let (song, _) = path.file_name()?.to_str()?.split_once(".mp3")?;
+    Some(song.replace('-', " "))
+}
+
+fn normalize(song: &str) -> String {
+    song.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
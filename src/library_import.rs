@@ -0,0 +1,279 @@
+use std::{fs, path::PathBuf};
+
+use chrono::DateTime;
+use roxmltree::{Document, Node};
+
+use crate::{
+    playlist_settings::{self, MAX_RATING},
+    scrobbler::TrackInfo,
+};
+
+//a track of another player's library
+struct ExternalTrack {
+    title: String,
+    artist: Option<String>,
+    file_name: Option<String>,
+    play_count: u32,
+    rating: Option<u8>,
+    last_played: Option<i64>,
+}
+
+impl ExternalTrack {
+    fn describe(&self) -> String {
+        match &self.artist {
+            Some(artist) => format!("{artist} - {}", self.title),
+            None => self.title.clone(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ImportReport {
+    pub player: &'static str,
+    pub matched_tracks: usize,
+    pub updated_play_counts: usize,
+    pub added_plays: u32,
+    pub updated_ratings: usize,
+    pub kept_ratings: Vec<String>,
+    pub unmatched_tracks: Vec<String>,
+}
+
+impl ImportReport {
+    pub fn print(&self) {
+        println!(
+            "matched {} tracks of the {} library, added {} plays to {} songs and updated {} ratings",
+            self.matched_tracks, self.player, self.added_plays, self.updated_play_counts, self.updated_ratings
+        );
+        if !self.kept_ratings.is_empty() {
+            println!("these songs were already rated differently here and kept their rating:");
+            for song in &self.kept_ratings {
+                println!("{song}");
+            }
+        }
+        if !self.unmatched_tracks.is_empty() {
+            println!("these tracks could not be matched to a song in the playlist folder:");
+            for track in &self.unmatched_tracks {
+                println!("{track}");
+            }
+        }
+    }
+}
+
+pub fn import_library(file: &str, paths: &[PathBuf]) -> Result<ImportReport, String> {
+    let contents = fs::read_to_string(file).map_err(|error| format!("failed to read {file} ({error})"))?;
+    let document = Document::parse(&contents).map_err(|error| format!("{file} is not valid xml ({error})"))?;
+    let root = document.root_element();
+    let (player, tracks) = match root.tag_name().name() {
+        "plist" => ("iTunes", read_itunes_tracks(root)),
+        "rhythmdb" => ("Rhythmbox", read_rhythmbox_tracks(root)),
+        _ => {
+            return Err(String::from(
+                "only an iTunes Library.xml or a Rhythmbox rhythmdb.xml can be imported",
+            ))
+        }
+    };
+
+    let track_infos = paths.iter().map(|path| TrackInfo::read(path)).collect::<Vec<_>>();
+    let mut report = ImportReport {
+        player,
+        ..ImportReport::default()
+    };
+    //the same library file is recognized again even if it is given with another relative path
+    let library = fs::canonicalize(file).map_or_else(|_| file.to_string(), |path| path.display().to_string());
+    let mut settings = playlist_settings::get_persistent_settings();
+    for track in &tracks {
+        let Some(index) = match_track(track, paths, &track_infos) else {
+            report.unmatched_tracks.push(track.describe());
+            continue;
+        };
+        report.matched_tracks += 1;
+        let song = crate::get_song_name(&paths[index]);
+        //importing the same library twice must not count the plays twice
+        let added_plays = settings.import_play_count(&library, &song, track.play_count);
+        if added_plays > 0 {
+            report.added_plays += added_plays;
+            report.updated_play_counts += 1;
+        }
+        if let Some(last_played) = track.last_played {
+            if settings
+                .get_song_last_played(&song)
+                .is_none_or(|other| last_played > other)
+            {
+                settings.set_song_last_played(&song, last_played);
+            }
+        }
+        if let Some(rating) = track.rating {
+            let mut song_settings = settings.get_song_settings(&song);
+            match song_settings.rating {
+                None => {
+                    song_settings.rating = Some(rating);
+                    settings.set_song_settings(song, song_settings);
+                    report.updated_ratings += 1;
+                }
+                Some(other) if other != rating => report.kept_ratings.push(format!(
+                    "{song} ({} here, {} in {player})",
+                    playlist_settings::format_rating(other),
+                    playlist_settings::format_rating(rating)
+                )),
+                Some(_) => (),
+            }
+        }
+    }
+    playlist_settings::update_settings(&settings);
+    Ok(report)
+}
+
+//tracks are matched by file name first, then by their tags and last by a similar name
+fn match_track(
+    track: &ExternalTrack,
+    paths: &[PathBuf],
+    track_infos: &[Option<TrackInfo>],
+) -> Option<usize> {
+    if let Some(file_name) = &track.file_name {
+        let by_file_name = paths.iter().position(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.eq_ignore_ascii_case(file_name))
+        });
+        if by_file_name.is_some() {
+            return by_file_name;
+        }
+    }
+    if let Some(artist) = &track.artist {
+        let by_tags = track_infos.iter().position(|info| {
+            info.as_ref().is_some_and(|info| {
+                normalize(&info.artist) == normalize(artist) && normalize(&info.title) == normalize(&track.title)
+            })
+        });
+        if by_tags.is_some() {
+            return by_tags;
+        }
+    }
+    let title = normalize(&track.title);
+    let full_name = normalize(&track.describe());
+    let by_name = paths
+        .iter()
+        .enumerate()
+        .filter(|(_, path)| {
+            let song = normalize(&crate::get_song_name(path));
+            song == title || song == full_name
+        })
+        .map(|(i, _)| i)
+        .collect::<Vec<usize>>();
+    //a title like "intro" may belong to many songs, so only a unique match counts
+    match by_name.as_slice() {
+        [index] => Some(*index),
+        _ => None,
+    }
+}
+
+fn read_itunes_tracks(root: Node) -> Vec<ExternalTrack> {
+    let Some(library) = root.children().find(|node| node.has_tag_name("dict")) else {
+        return Vec::new();
+    };
+    let Some((_, tracks)) = get_dict_entries(library)
+        .into_iter()
+        .find(|(key, _)| key == "Tracks")
+    else {
+        return Vec::new();
+    };
+    get_dict_entries(tracks)
+        .into_iter()
+        .filter_map(|(_, track)| {
+            let entries = get_dict_entries(track);
+            let get = |key: &str| {
+                entries
+                    .iter()
+                    .find(|(other, _)| other == key)
+                    .map(|(_, value)| *value)
+            };
+            let text = |key: &str| get(key).and_then(|value| value.text()).map(String::from);
+            let title = text("Name")?;
+            //itunes rates from 0 to 100 in steps of 20 per star
+            let rating = text("Rating")
+                .and_then(|rating| rating.parse::<u32>().ok())
+                .filter(|rating| *rating > 0 && !get("Rating Computed").is_some_and(|value| value.has_tag_name("true")))
+                .map(|rating| u8::try_from((rating + 10) / 20).unwrap_or(MAX_RATING).min(MAX_RATING));
+            let loved = get("Loved").is_some_and(|value| value.has_tag_name("true"));
+            Some(ExternalTrack {
+                title,
+                artist: text("Artist"),
+                file_name: text("Location").and_then(|location| get_file_name(&location)),
+                play_count: text("Play Count")
+                    .and_then(|play_count| play_count.parse().ok())
+                    .unwrap_or_default(),
+                rating: rating.or(loved.then_some(MAX_RATING)),
+                last_played: text("Play Date UTC")
+                    .and_then(|date| DateTime::parse_from_rfc3339(&date).ok())
+                    .map(|date| date.timestamp()),
+            })
+        })
+        .collect()
+}
+
+fn read_rhythmbox_tracks(root: Node) -> Vec<ExternalTrack> {
+    root.children()
+        .filter(|node| node.has_tag_name("entry") && node.attribute("type") == Some("song"))
+        .filter_map(|entry| {
+            let text = |tag: &str| {
+                entry
+                    .children()
+                    .find(|node| node.has_tag_name(tag))
+                    .and_then(|node| node.text())
+                    .map(String::from)
+            };
+            Some(ExternalTrack {
+                title: text("title")?,
+                artist: text("artist"),
+                file_name: text("location").and_then(|location| get_file_name(&location)),
+                play_count: text("play-count")
+                    .and_then(|play_count| play_count.parse().ok())
+                    .unwrap_or_default(),
+                rating: text("rating")
+                    .and_then(|rating| rating.parse::<u8>().ok())
+                    .filter(|rating| *rating > 0)
+                    .map(|rating| rating.min(MAX_RATING)),
+                last_played: text("last-played").and_then(|last_played| last_played.parse().ok()),
+            })
+        })
+        .collect()
+}
+
+//plist dictionaries are written as alternating key and value elements
+fn get_dict_entries<'a, 'input>(dict: Node<'a, 'input>) -> Vec<(String, Node<'a, 'input>)> {
+    let mut entries = Vec::new();
+    let mut elements = dict.children().filter(Node::is_element);
+    while let (Some(key), Some(value)) = (elements.next(), elements.next()) {
+        entries.push((key.text().unwrap_or_default().to_string(), value));
+    }
+    entries
+}
+
+//locations are file urls like file:///Users/me/Music/My%20Song.mp3
+fn get_file_name(location: &str) -> Option<String> {
+    let file_name = location.rsplit(['/', '\\']).next()?;
+    let bytes = file_name.as_bytes();
+    let mut decoded = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| file_name.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        if let Some(byte) = escaped {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok().filter(|name| !name.is_empty())
+}
+
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
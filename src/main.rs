@@ -8,12 +8,17 @@
 #![allow(clippy::cast_precision_loss)]
 #![allow(clippy::assigning_clones)]
 
+use chrono::Local;
 use crash_reporter::CrashReporter;
+use history::{Listen, ListenEnd};
+use last_session::LastSession;
 use playlist_settings::AfterSong;
 use playlist_settings::PersistentSettings;
 use playlist_settings::SessionSettings;
 use rdev::Event;
 use rodio::{Decoder, OutputStream, Sink, Source};
+use song_error::SongError;
+use trimmed_source::TrimmedSource;
 use std::io::{BufReader, Write};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
@@ -22,16 +27,32 @@ use std::{fs, thread};
 use std::fs::File;
 
 mod crash_reporter;
+mod filter;
 mod handle_input;
+mod history;
+mod last_session;
+mod library;
+mod library_import;
+mod playlist_file;
 mod playlist_settings;
+mod scrobbler;
+mod settings_csv;
+mod shuffle_strategy;
+mod song_error;
+mod stats;
+mod trimmed_source;
 mod utils;
 
 fn main() {
     println!("Music player started\nType 'commands' to see available commands");
 
     let mut crash_reporter = CrashReporter::new();
-    let paths = get_song_paths();
+    let mut paths = get_song_paths();
     setup_playlist_settings_file();
+    playlist_settings::check_machine_id();
+    if std::env::args().any(|arg| arg == "--check-library") {
+        library::check_library_on_startup(&paths);
+    }
 
     let volume = playlist_settings::get_persistent_settings().volume;
     if (volume - 1.0).abs() > f32::EPSILON {
@@ -39,23 +60,35 @@ fn main() {
     }
 
     let mut session_settings = SessionSettings::default();
+    if let Some(seed) = get_seed_argument() {
+        session_settings.set_seed(seed);
+        println!("shuffle seed: {seed}");
+    }
 
     let (_stream, stream_handle) =
         OutputStream::try_default().expect("Failed to get default output stream");
     let audio_player = Sink::try_new(&stream_handle).expect("Failed to create a new Sink");
-    let index = get_next_song_index(&mut session_settings, &paths);
-    crash_reporter.next_song(get_song_name(&paths[index]), session_settings.clone());
-    let (source, _, song_name) = play_next_song(index, &paths, &mut session_settings);
-    session_settings.song_duration = source
-        .total_duration();
-    audio_player.append(source);
-    let mut persistent_settings = playlist_settings::get_persistent_settings();
-    let song_settings = persistent_settings.get_song_settings(&song_name);
-    let track_volume = song_settings.song_volume * volume;
-    audio_player.set_volume(track_volume);
-    persistent_settings.set_song_probability(&song_name, 0);
-    persistent_settings.accumulate_play_count(&song_name);
-    playlist_settings::update_settings(&persistent_settings);
+    let resumed_song = get_last_session_to_resume().and_then(|last_session| {
+        let index = last_session.restore(&mut session_settings, &paths)?;
+        match resume_song(index, last_session.get_position(), &paths, &mut session_settings) {
+            Ok(song) => Some(song),
+            Err(error) => {
+                mark_song_as_broken(&paths[index], &error);
+                None
+            }
+        }
+    });
+    let song_name = if let Some((source, song_name)) = resumed_song {
+        audio_player.append(source);
+        song_name
+    } else {
+        let (source, _, song_name) =
+            play_next_playable_song(&paths, &mut session_settings, &mut crash_reporter);
+        audio_player.append(source);
+        song_name
+    };
+    let song_settings = playlist_settings::get_persistent_settings().get_song_settings(&song_name);
+    audio_player.set_volume(session_settings.playback_playlist_volume() * song_settings.song_volume);
 
     crash_reporter.set_session_settings(session_settings.clone());
 
@@ -67,6 +100,9 @@ fn main() {
         new_messages_clone.lock().unwrap().push(input);
     });
 
+    let library_events = Arc::new(Mutex::new(Vec::new()));
+    let _library_watcher = library::watch_playlist_directory(Arc::clone(&library_events));
+
     let new_key_events = Arc::new(Mutex::new(Vec::new()));
     let new_key_events_clone = Arc::clone(&new_key_events);
 
@@ -77,9 +113,13 @@ fn main() {
         .expect("Failed to listen for keyboard events");
     });
 
+    let mut last_session_saved = Instant::now();
+    let mut last_session_song = String::new();
     loop {
         crash_reporter.set_session_settings(session_settings.clone());
 
+        check_library_changes(&library_events, &audio_player, &mut paths, &mut session_settings);
+
         check_new_commands(
             &new_messages,
             &audio_player,
@@ -96,16 +136,36 @@ fn main() {
             &mut crash_reporter,
         );
 
-        if audio_player.empty() {
+        check_song_loop(&audio_player, &mut session_settings);
+
+        save_last_session(
+            &audio_player,
+            &session_settings,
+            &paths,
+            &mut last_session_saved,
+            &mut last_session_song,
+        );
+
+        if audio_player.empty() && !paths.is_empty() {
+            end_listen(&session_settings, session_settings.song_progress(), ListenEnd::Completed);
             audio_player.clear();
-            let index = get_next_song_index(&mut session_settings, &paths);
-            crash_reporter.next_song(get_song_name(&paths[index]), session_settings.clone());
             let (source, _, song_name) = match session_settings.after_song {
                 AfterSong::PlaySong(next_song) => {
-                    let song = index_song(&paths, next_song);
-                    (song.0, next_song, song.1)
+                    crash_reporter
+                        .next_song(get_song_name(&paths[next_song]), session_settings.clone());
+                    match play_next_song(next_song, &paths, &mut session_settings) {
+                        Ok(song) => song,
+                        Err(error) => {
+                            mark_song_as_broken(&paths[next_song], &error);
+                            play_next_playable_song(
+                                &paths,
+                                &mut session_settings,
+                                &mut crash_reporter,
+                            )
+                        }
+                    }
                 }
-                _ => play_next_song(index, &paths, &mut session_settings),
+                _ => play_next_playable_song(&paths, &mut session_settings, &mut crash_reporter),
             };
             audio_player.append(source);
             let song_settings =
@@ -125,6 +185,71 @@ fn main() {
     }
 }
 
+//"--resume" resumes the last session without asking, "--no-resume" starts a new session without asking
+fn get_last_session_to_resume() -> Option<LastSession> {
+    if std::env::args().any(|arg| arg == "--no-resume") {
+        return None;
+    }
+    let last_session = LastSession::load()?;
+    if std::env::args().any(|arg| arg == "--resume") {
+        return Some(last_session);
+    }
+    println!(
+        "type 'resume' to continue the last session with {} or press enter to start a new session",
+        last_session.describe()
+    );
+    match utils::get_console_input().trim().to_lowercase().as_str() {
+        "resume" | "r" | "y" | "yes" => Some(last_session),
+        _ => None,
+    }
+}
+
+fn resume_song(
+    index: usize,
+    position: Duration,
+    paths: &[PathBuf],
+    session_settings: &mut SessionSettings,
+) -> Result<(SongSource, String), SongError> {
+    let (mut source, song_name) = index_song(paths, index)?;
+    let position = match source.try_seek(position) {
+        Ok(()) => position,
+        Err(error) => {
+            println!("Failed to resume at {}: {error}", utils::format_duration(&position));
+            Duration::ZERO
+        }
+    };
+    session_settings.current_song_index = index;
+    session_settings.current_song_name = song_name.clone();
+    session_settings.duration_start = Instant::now();
+    session_settings.song_started = Local::now().timestamp();
+    session_settings.song_duration = source.total_duration();
+    session_settings.reset_song_progress();
+    session_settings.seek_song_progress(position, false);
+    session_settings.stop_loop();
+    println!(
+        "Resuming: {song_name} at {} ({})",
+        utils::format_duration(&position),
+        session_settings.format_song_duration()
+    );
+    Ok((source, song_name))
+}
+
+fn get_seed_argument() -> Option<u64> {
+    let arguments = std::env::args().collect::<Vec<String>>();
+    let seed = arguments.iter().enumerate().find_map(|(i, argument)| {
+        if argument == "--seed" {
+            arguments.get(i + 1).cloned()
+        } else {
+            argument.strip_prefix("--seed=").map(String::from)
+        }
+    })?;
+    let seed = seed.parse::<u64>().ok();
+    if seed.is_none() {
+        println!("the seed has to be a positive integer like \"--seed 42\", a random seed is used instead");
+    }
+    seed
+}
+
 fn setup_playlist_settings_file() {
     let playlist_settings_path = Path::new("playlist-settings.json");
     if !playlist_settings_path.exists() {
@@ -144,22 +269,147 @@ fn get_default_distribution(len: usize) -> Vec<u32> {
 }
 
 fn get_song_paths() -> Vec<PathBuf> {
-    fs::read_dir("playlist")
-        .expect("Failed to find \"playlist\" directory. Please create a folder called \"playlist\" in the \"music-player\" directory.")
-        .map(|path| {
-            path.expect("Failed to read paths in \"playlist\" directory")
-                .path()
-        })
-        //the first path in the playlist is reserved for a subfolder to synchronize the playlist to google drive
-        .skip(1)
-        .collect::<Vec<PathBuf>>()
+    assert!(
+        Path::new("playlist").is_dir(),
+        "Failed to find \"playlist\" directory. Please create a folder called \"playlist\" in the \"music-player\" directory."
+    );
+    get_song_paths_in(Path::new("playlist"))
+}
+
+//songs can be sorted into subfolders, the hidden folders google drive uses to synchronize the playlist (like .tmp.drivedownload) are skipped
+fn get_song_paths_in(folder: &Path) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let entries = fs::read_dir(folder).expect("Failed to read paths in \"playlist\" directory");
+    for path in entries.map(|entry| entry.expect("Failed to read paths in \"playlist\" directory").path()) {
+        let is_hidden = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with('.'));
+        if is_hidden {
+            continue;
+        }
+        //files that are still being downloaded don't have the mp3 extension yet
+        if path.is_dir() {
+            paths.extend(get_song_paths_in(&path));
+        } else if path.is_file() && path.extension().is_some_and(|extension| extension == "mp3") {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+fn play_next_playable_song(
+    paths: &[PathBuf],
+    session_settings: &mut SessionSettings,
+    crash_reporter: &mut CrashReporter,
+) -> (SongSource, usize, String) {
+    loop {
+        let index = get_next_song_index(session_settings, paths);
+        crash_reporter.next_song(get_song_name(&paths[index]), session_settings.clone());
+        match play_next_song(index, paths, session_settings) {
+            Ok(song) => return song,
+            Err(error) => mark_song_as_broken(&paths[index], &error),
+        }
+    }
+}
+
+//the play count only grows once the play threshold is reached, so songs that are skipped right away don't count
+fn end_listen(session_settings: &SessionSettings, position: Duration, end: ListenEnd) {
+    let song = &session_settings.current_song_name;
+    if song.is_empty() {
+        return;
+    }
+    let completed = end == ListenEnd::Completed;
+    let position = match session_settings.song_duration {
+        Some(song_duration) if completed => song_duration,
+        Some(song_duration) => position.min(song_duration),
+        None => position,
+    };
+    let listened = session_settings.get_listened_time(position);
+    let mut settings = playlist_settings::get_persistent_settings();
+    settings.record_listen(song, listened, completed);
+    let is_play = settings
+        .play_threshold
+        .is_reached(listened, session_settings.song_duration);
+    if is_play {
+        settings.accumulate_play_count(song);
+    }
+    if settings.get_song_settings(song).resume_position {
+        //a song that was played to the end starts from the beginning the next time
+        let position = session_settings
+            .song_duration
+            .map_or(Some(position), |song_duration| (position < song_duration).then_some(position));
+        settings.set_song_position(song, position.filter(|_| !completed));
+    }
+    playlist_settings::update_settings(&settings);
+    let listen = Listen::new(session_settings, listened.as_secs(), end);
+    history::append_listen(&listen);
+    if is_play && settings.scrobble_log {
+        if let Some(path) = scrobbler::find_song_path(song) {
+            scrobbler::append_scrobble(&path, &listen);
+        }
+    }
+}
+
+fn seek_song(audio_player: &Sink, session_settings: &mut SessionSettings, position: Duration) -> bool {
+    match audio_player.try_seek(position) {
+        Ok(()) => {
+            session_settings.seek_song_progress(position, audio_player.is_paused());
+            true
+        }
+        Err(error) => {
+            println!("Failed to jump to {}: {error}", utils::format_duration(&position));
+            false
+        }
+    }
+}
+
+//the a-b loop jumps back to its start once the end is reached
+fn check_song_loop(audio_player: &Sink, session_settings: &mut SessionSettings) {
+    let (Some(loop_start), Some(loop_end)) = (session_settings.loop_start, session_settings.loop_end) else {
+        return;
+    };
+    if audio_player.is_paused() || audio_player.empty() || session_settings.song_progress() < loop_end {
+        return;
+    }
+    if !seek_song(audio_player, session_settings, loop_start) {
+        session_settings.stop_loop();
+        println!("the loop is stopped");
+    }
+}
+
+//the session is also saved while playing, so it can be resumed after a crash or a closed terminal
+fn save_last_session(
+    audio_player: &Sink,
+    session_settings: &SessionSettings,
+    paths: &[PathBuf],
+    last_saved: &mut Instant,
+    last_song: &mut String,
+) {
+    if last_saved.elapsed() < LAST_SESSION_INTERVAL && *last_song == session_settings.current_song_name {
+        return;
+    }
+    let position = session_settings.song_position(audio_player.is_paused());
+    LastSession::new(session_settings, position, paths).save();
+    *last_saved = Instant::now();
+    last_song.clone_from(&session_settings.current_song_name);
+}
+
+fn mark_song_as_broken(path: &Path, error: &SongError) {
+    let song = get_song_name(path);
+    println!("Failed to play {song}: {error}");
+    println!("{song} is now marked as broken and will be skipped until 'check library' finds it playable again");
+    let mut song_settings = playlist_settings::get_persistent_settings().get_song_settings(&song);
+    song_settings.is_broken = true;
+    playlist_settings::update_song_settings(song, song_settings);
 }
 
 fn play_next_song(
     index: usize,
     paths: &[PathBuf],
     session_settings: &mut SessionSettings,
-) -> (Decoder<BufReader<File>>, usize, String) {
+) -> Result<(SongSource, usize, String), SongError> {
+    let (mut source, file_name) = index_song(paths, index)?;
     let mut persistent_settings = playlist_settings::get_persistent_settings();
     if session_settings.shuffle {
         for i in 0..paths.len() {
@@ -167,20 +417,23 @@ fn play_next_song(
         }
         persistent_settings.set_song_probability(paths[index].to_str().expect("path has no name"), 0);
     }
-    persistent_settings.accumulate_play_count(paths[index].to_str().expect("path has no name"));
+    persistent_settings.record_play(&file_name, Local::now().timestamp());
     playlist_settings::update_settings(&persistent_settings);
-    let (source, file_name) = index_song(paths, index);
+    session_settings.shuffle_strategy.song_played(&file_name);
     session_settings.current_song_index = index;
     session_settings.current_song_name = file_name.clone();
     session_settings.duration_start = Instant::now();
+    session_settings.song_started = Local::now().timestamp();
     session_settings.song_duration = source
         .total_duration();
     session_settings.reset_song_progress();
+    session_settings.stop_loop();
     println!(
         "Now playing: {} ({})",
         session_settings.current_song_name,
         session_settings.format_song_duration(),
     );
+    resume_song_position(&mut source, &persistent_settings, session_settings);
     let song_settings = persistent_settings.get_song_settings(&file_name);
     let song_volume = song_settings.song_volume;
     let playlist_volume = persistent_settings.volume;
@@ -191,57 +444,161 @@ fn play_next_song(
             (song_volume * playlist_volume * 100.0).round()
         );
     }
-    if song_settings.starred {
-        println!("This song is starred");
+    if let Some(rating) = song_settings.rating {
+        println!("Rating: {}", playlist_settings::format_rating(rating));
+    }
+    Ok((source, index, file_name))
+}
+
+//songs that remember their position continue where they were stopped
+fn resume_song_position(
+    source: &mut SongSource,
+    persistent_settings: &PersistentSettings,
+    session_settings: &mut SessionSettings,
+) {
+    let song = &session_settings.current_song_name;
+    if !persistent_settings.get_song_settings(song).resume_position {
+        return;
+    }
+    let Some(position) = persistent_settings.get_song_position(song) else {
+        return;
+    };
+    match source.try_seek(position) {
+        Ok(()) => {
+            session_settings.seek_song_progress(position, false);
+            println!("Resuming at {}", utils::format_duration(&position));
+        }
+        Err(error) => println!("Failed to resume at {}: {error}", utils::format_duration(&position)),
     }
-    (source, index, file_name)
 }
 
 fn get_next_song_index(session_settings: &mut SessionSettings, paths: &[PathBuf]) -> usize {
     let settings = playlist_settings::get_persistent_settings();
+    if session_settings.filter.is_some()
+        && !paths
+            .iter()
+            .any(|path| is_song_selectable(path, &settings, session_settings))
+    {
+        println!("no song matches the filter anymore, the filter is removed");
+        session_settings.filter = None;
+    }
+    if session_settings.active_playlist.is_some()
+        && !paths
+            .iter()
+            .any(|path| is_song_selectable(path, &settings, session_settings))
+    {
+        println!("no song of the active playlist can be played, playing the whole library instead");
+        session_settings.active_playlist = None;
+    }
+    //rotation constraints are relaxed if they would leave no song to play
+    let now = Local::now().timestamp();
+    let respect_rotation = settings.rotation.is_enabled()
+        && paths.iter().any(|path| {
+            is_song_selectable(path, &settings, session_settings)
+                && !settings.violates_rotation(&get_song_name(path), now)
+        });
+    if settings.rotation.is_enabled() && !respect_rotation {
+        println!("every song violates the rotation constraints, they are ignored for the next song");
+    }
+    let is_candidate = |path: &Path| {
+        is_song_selectable(path, &settings, session_settings)
+            && !(respect_rotation && settings.violates_rotation(&get_song_name(path), now))
+    };
     if session_settings.shuffle {
-        let mut modified_song_probability_distribution = Vec::new();
-        for i in 0..paths.len() {
-            let song_settings = settings.get_song_settings(&get_song_name(&paths[i]));
-            if session_settings.exclude_lyrics && song_settings.has_lyrics {
-                modified_song_probability_distribution.push(0);
+        let candidates = (0..paths.len())
+            .filter(|i| is_candidate(&paths[*i]))
+            .collect::<Vec<usize>>();
+        assert!(!candidates.is_empty(), "{NO_SELECTABLE_SONGS}");
+        session_settings.shuffle_strategy.select(
+            &candidates,
+            paths,
+            &settings,
+            &mut session_settings.random,
+        )
+    } else if let Some(playlist_songs) = session_settings.get_active_playlist(&settings) {
+        //songs of a playlist are played in the order of the playlist instead of the order of the folder
+        let current_position = playlist_songs
+            .iter()
+            .position(|song| song == &session_settings.current_song_name);
+        let start = current_position.map_or(0, |position| position + 1);
+        let mut next_song = None;
+        for i in 0..playlist_songs.len() {
+            let song = &playlist_songs[(start + i) % playlist_songs.len()];
+            let Some(index) = paths.iter().position(|path| &get_song_name(path) == song) else {
+                continue;
+            };
+            if !is_candidate(&paths[index]) {
                 continue;
             }
-            let p = settings.get_probability_distribution(paths)[i];
-            let star_factor = if song_settings.starred { 2 } else { 1 };
-            modified_song_probability_distribution.push(p * star_factor);
+            next_song = Some(index);
+            break;
         }
-        utils::weighted_random_selection(
-            &modified_song_probability_distribution,
-            &mut session_settings.random,
-        )
+        next_song.expect(NO_SELECTABLE_SONGS)
     } else {
         let mut next_song = None;
         for i in 0..paths.len() {
             let i = (session_settings.current_song_index + i + 1) % paths.len();
-            if session_settings.exclude_lyrics
-                && settings
-                    .get_song_settings(&get_song_name(&paths[i]))
-                    .has_lyrics
-            {
+            if !is_candidate(&paths[i]) {
                 continue;
             }
             next_song = Some(i);
             break;
         }
-        next_song.expect(
-            "exclude lyrics mode is enabled but all songs in the playlist are set to have lyrics",
-        )
+        next_song.expect(NO_SELECTABLE_SONGS)
+    }
+}
+
+const LAST_SESSION_INTERVAL: Duration = Duration::from_secs(10);
+
+const NO_SELECTABLE_SONGS: &str =
+    "there are no playable songs left in the playlist (songs with lyrics are excluded in no lyrics mode and broken songs are always skipped)";
+
+fn is_song_selectable(
+    path: &Path,
+    settings: &PersistentSettings,
+    session_settings: &SessionSettings,
+) -> bool {
+    let song = get_song_name(path);
+    let song_settings = settings.get_song_settings(&song);
+    if song_settings.is_broken {
+        return false;
+    }
+    if session_settings.active_playlist.is_some() {
+        let in_playlist = session_settings
+            .get_active_playlist(settings)
+            .is_some_and(|songs| songs.contains(&song));
+        if !in_playlist {
+            return false;
+        }
+    }
+    if session_settings.exclude_lyrics && song_settings.has_lyrics() {
+        return false;
+    }
+    match &session_settings.filter {
+        Some(filter) => filter.matches(path, settings),
+        None => true,
     }
 }
 
-fn index_song(paths: &[PathBuf], index: usize) -> (Decoder<BufReader<File>>, String) {
+//songs are played between their trim points, so their duration and progress leave out the trimmed parts
+type SongSource = TrimmedSource<Decoder<BufReader<File>>>;
+
+fn index_song(paths: &[PathBuf], index: usize) -> Result<(SongSource, String), SongError> {
     let path = &paths[index];
     let file_name = get_song_name(path);
-    let file = File::open(path).expect("File does not exist in the specified directory");
+    let song_settings = playlist_settings::get_persistent_settings().get_song_settings(&file_name);
+    let source = TrimmedSource::new(
+        decode_song(path)?,
+        song_settings.get_trim_start(),
+        song_settings.get_trim_end(),
+    );
+    Ok((source, file_name))
+}
+
+fn decode_song(path: &Path) -> Result<Decoder<BufReader<File>>, SongError> {
+    let file = File::open(path)?;
     let reader = BufReader::new(file);
-    let source = Decoder::new(reader).expect("Failed to decode the MP3 file");
-    (source, file_name)
+    Ok(Decoder::new(reader)?)
 }
 
 fn check_new_commands(
@@ -298,3 +655,33 @@ fn check_new_key_events(
         );
     }
 }
+
+fn check_library_changes(
+    library_events: &Arc<Mutex<Vec<notify::Event>>>,
+    audio_player: &Sink,
+    paths: &mut Vec<PathBuf>,
+    session_settings: &mut SessionSettings,
+) {
+    let mut events = Vec::new();
+    let mut library_events = library_events.lock().unwrap();
+    events.append(&mut *library_events);
+    if events.is_empty() {
+        return;
+    }
+    let changed_paths = events
+        .into_iter()
+        .filter(|event| {
+            matches!(
+                event.kind,
+                notify::EventKind::Create(_) | notify::EventKind::Modify(_)
+            )
+        })
+        .flat_map(|event| event.paths)
+        .collect::<Vec<PathBuf>>();
+    library::update_library(paths, session_settings, &changed_paths);
+    //without songs no next song can be chosen, so playback waits until songs are added again
+    if paths.is_empty() && !audio_player.is_paused() {
+        println!("every song was removed from the playlist folder, playback is paused until songs are added");
+        handle_input::pause(session_settings, audio_player);
+    }
+}
@@ -0,0 +1,102 @@
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub enum PlaylistFormat {
+    M3u,
+    Pls,
+}
+
+impl PlaylistFormat {
+    pub fn from_file_name(file: &str) -> Option<Self> {
+        let extension = Path::new(file).extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "m3u" | "m3u8" => Some(PlaylistFormat::M3u),
+            "pls" => Some(PlaylistFormat::Pls),
+            _ => None,
+        }
+    }
+}
+
+pub fn export_playlist(file: &str, songs: &[PathBuf], format: &PlaylistFormat) -> Result<(), String> {
+    //absolute paths keep working no matter where the playlist file is moved to
+    let songs = songs
+        .iter()
+        .map(|path| {
+            let song = crate::get_song_name(path);
+            let path = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+            (song, path.display().to_string())
+        })
+        .collect::<Vec<(String, String)>>();
+    //writing to a string can not fail
+    let mut contents = String::new();
+    match format {
+        PlaylistFormat::M3u => {
+            contents += "#EXTM3U\n";
+            for (song, path) in &songs {
+                writeln!(contents, "#EXTINF:-1,{song}\n{path}").unwrap();
+            }
+        }
+        PlaylistFormat::Pls => {
+            contents += "[playlist]\n";
+            for (i, (song, path)) in songs.iter().enumerate() {
+                let number = i + 1;
+                writeln!(contents, "File{number}={path}\nTitle{number}={song}\nLength{number}=-1").unwrap();
+            }
+            writeln!(contents, "NumberOfEntries={}\nVersion=2", songs.len()).unwrap();
+        }
+    }
+    fs::write(file, contents).map_err(|error| format!("failed to write {file} ({error})"))
+}
+
+//returns the entries in the order of the file, they still have to be resolved against the library
+pub fn read_playlist(file: &str) -> Result<Vec<String>, String> {
+    let format = PlaylistFormat::from_file_name(file)
+        .ok_or_else(|| String::from("only .m3u, .m3u8 and .pls files can be imported"))?;
+    let contents = fs::read_to_string(file).map_err(|error| format!("failed to read {file} ({error})"))?;
+    let contents = contents.trim_start_matches('\u{feff}');
+    let entries = match format {
+        PlaylistFormat::M3u => contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(String::from)
+            .collect(),
+        PlaylistFormat::Pls => {
+            let mut entries = contents
+                .lines()
+                .filter_map(|line| {
+                    let (key, value) = line.trim().split_once('=')?;
+                    let number = key.trim().to_lowercase().strip_prefix("file")?.parse::<u32>().ok()?;
+                    Some((number, value.trim().to_string()))
+                })
+                .collect::<Vec<(u32, String)>>();
+            entries.sort_by_key(|(number, _)| *number);
+            entries.into_iter().map(|(_, entry)| entry).collect()
+        }
+    };
+    Ok(entries)
+}
+
+//entries are matched by their file name, so playlists from other machines and folders still work
+pub fn resolve_entry(entry: &str, paths: &[PathBuf]) -> Option<usize> {
+    let entry = entry.strip_prefix("file://").unwrap_or(entry);
+    let file_name = entry.rsplit(['/', '\\']).next().unwrap_or(entry);
+    paths
+        .iter()
+        .position(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name == file_name)
+        })
+        .or_else(|| {
+            let file_name = file_name.to_lowercase().replace("%20", " ");
+            paths.iter().position(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.to_lowercase() == file_name)
+            })
+        })
+}
@@ -1,11 +1,17 @@
 use std::{
-    fs, path::PathBuf, time::{Duration, Instant}
+    fmt::Display, fs, path::{Path, PathBuf}, time::{Duration, Instant}
 };
 
-use rand::rngs::ThreadRng;
+use chrono::Local;
+use rand::{rngs::StdRng, SeedableRng};
 use serde::{Deserialize, Serialize};
 
-use crate::utils;
+use crate::{
+    filter::Filter,
+    settings_csv::SettingsImport,
+    shuffle_strategy::{ShuffleStrategy, WeightedStrategy},
+    utils,
+};
 
 #[derive(Clone)]
 pub struct SessionSettings {
@@ -16,10 +22,25 @@ pub struct SessionSettings {
     pub current_song_index: usize,
     pub current_song_name: String,
     pub duration_start: Instant,
+    pub song_started: i64,
     song_progress: Duration,
+    //seeking changes the position without listening, so the time listened is counted up to the last seek
+    listened_before_seek: Duration,
+    seek_position: Duration,
     pub song_duration: Option<Duration>,
     pub after_song: AfterSong,
-    pub random: ThreadRng,
+    pub filter: Option<Filter>,
+    pub active_playlist: Option<String>,
+    //the name and songs of a playlist file that is played without saving it
+    pub queued_playlist: Option<(String, Vec<String>)>,
+    pub shuffle_strategy: Box<dyn ShuffleStrategy>,
+    seed: u64,
+    pub random: StdRng,
+    //an imported csv file is only applied after its changes were previewed
+    pub pending_settings_import: Option<SettingsImport>,
+    //the a-b loop repeats the current song between its start and end until it is stopped
+    pub loop_start: Option<Duration>,
+    pub loop_end: Option<Duration>,
 }
 
 impl SessionSettings {
@@ -35,6 +56,15 @@ impl SessionSettings {
         self.song_progress + self.duration_start.elapsed()
     }
 
+    //while paused the time since duration_start is not part of the progress
+    pub fn song_position(&self, is_paused: bool) -> Duration {
+        if is_paused {
+            self.song_progress
+        } else {
+            self.song_progress()
+        }
+    }
+
     pub fn format_song_duration(&self) -> String {
         match self.song_duration {
             Some(duration) => utils::format_duration(&duration),
@@ -46,13 +76,52 @@ impl SessionSettings {
         self.song_progress += progress;
     }
     
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    //the same seed replays the same sequence of shuffled songs as long as the playlist settings are the same
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.random = StdRng::seed_from_u64(seed);
+    }
+
     pub fn reset_song_progress(&mut self) {
         self.song_progress = Duration::ZERO;
+        self.listened_before_seek = Duration::ZERO;
+        self.seek_position = Duration::ZERO;
+    }
+
+    //after seeking the progress starts counting from the new position
+    pub fn seek_song_progress(&mut self, position: Duration, is_paused: bool) {
+        self.listened_before_seek = self.get_listened_time(self.song_position(is_paused));
+        self.seek_position = position;
+        self.song_progress = position;
+        self.duration_start = Instant::now();
+    }
+
+    //the time the current song was listened to until it reached the given position
+    pub fn get_listened_time(&self, position: Duration) -> Duration {
+        self.listened_before_seek + position.saturating_sub(self.seek_position)
+    }
+
+    pub fn get_active_playlist<'a>(&'a self, settings: &'a PersistentSettings) -> Option<&'a [String]> {
+        let playlist = self.active_playlist.as_ref()?;
+        match &self.queued_playlist {
+            Some((name, songs)) if name == playlist => Some(songs),
+            _ => settings.find_playlist(playlist).map(|(_, songs)| songs.as_slice()),
+        }
+    }
+
+    pub fn stop_loop(&mut self) {
+        self.loop_start = None;
+        self.loop_end = None;
     }
 }
 
 impl Default for SessionSettings {
     fn default() -> Self {
+        let seed = rand::random();
         Self {
             is_muted: false,
             key_events_enabled: true,
@@ -61,10 +130,21 @@ impl Default for SessionSettings {
             current_song_index: 0,
             current_song_name: String::new(),
             duration_start: Instant::now(),
+            song_started: 0,
             song_progress: Duration::ZERO,
+            listened_before_seek: Duration::ZERO,
+            seek_position: Duration::ZERO,
             song_duration: None,
             after_song: AfterSong::Continue,
-            random: rand::thread_rng(),
+            filter: None,
+            active_playlist: None,
+            queued_playlist: None,
+            shuffle_strategy: Box::new(WeightedStrategy),
+            seed,
+            random: StdRng::seed_from_u64(seed),
+            pending_settings_import: None,
+            loop_start: None,
+            loop_end: None,
         }
     }
 }
@@ -82,6 +162,287 @@ pub struct PersistentSettings {
     song_settings: Vec<(String, SongSettings)>,
     song_probability_distribution: Vec<(String, u32)>,
     song_play_count: Vec<(String, u32)>,
+    #[serde(default = "default_rating_weights")]
+    rating_weights: [u32; MAX_RATING as usize + 1],
+    #[serde(default = "default_smart_playlists")]
+    smart_playlists: Vec<(String, String)>,
+    #[serde(default)]
+    playlists: Vec<(String, Vec<String>)>,
+    #[serde(default)]
+    song_last_played: Vec<(String, i64)>,
+    #[serde(default)]
+    pub probability_growth: ProbabilityGrowth,
+    #[serde(default)]
+    pub rotation: RotationConstraints,
+    #[serde(default)]
+    recent_plays: Vec<(String, i64)>,
+    #[serde(default)]
+    song_skips: Vec<(String, SkipStats)>,
+    //how much a song that is always skipped loses of its weight in percent, 0 disables the penalty
+    #[serde(default)]
+    pub skip_penalty: u32,
+    #[serde(default)]
+    song_listen_time: Vec<(String, ListenTime)>,
+    #[serde(default)]
+    pub play_threshold: PlayThreshold,
+    #[serde(default = "default_scrobble_log")]
+    pub scrobble_log: bool,
+    //identifies the settings of this machine when they are merged with the settings of another machine
+    //settings from before machine ids existed get one the first time they are loaded
+    #[serde(default)]
+    machine_id: String,
+    #[serde(default)]
+    merged_machines: Vec<MergedMachine>,
+    //where songs that resume their position were stopped, in seconds
+    #[serde(default)]
+    song_positions: Vec<(String, u64)>,
+    //named positions inside songs in seconds, like the drop of a song
+    #[serde(default)]
+    song_bookmarks: Vec<(String, Vec<(String, u64)>)>,
+    #[serde(default)]
+    imported_libraries: Vec<ImportedLibrary>,
+}
+
+//the play counts of every machine are kept apart, so merging the same machines again never counts a play twice
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct MergedMachine {
+    machine_id: String,
+    last_merged: i64,
+    play_counts: Vec<(String, u32)>,
+}
+
+impl MergedMachine {
+    fn get_play_count(&self, song: &str) -> u32 {
+        self.play_counts
+            .iter()
+            .find(|(other, _)| song == other)
+            .map_or(0, |(_, play_count)| *play_count)
+    }
+}
+
+//the play counts of another player's library when it was last imported, so importing it again only adds the new plays
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct ImportedLibrary {
+    file: String,
+    play_counts: Vec<(String, u32)>,
+}
+
+#[derive(Default)]
+pub struct MergeReport {
+    pub added_plays: u32,
+    pub taken_song_settings: Vec<String>,
+    pub added_playlists: Vec<String>,
+    pub conflicts: Vec<String>,
+}
+
+impl MergeReport {
+    pub fn print(&self) {
+        println!(
+            "added {} plays and took the newer settings of {} songs",
+            self.added_plays,
+            self.taken_song_settings.len()
+        );
+        for song in &self.taken_song_settings {
+            println!("{song}");
+        }
+        for playlist in &self.added_playlists {
+            println!("added playlist {playlist}");
+        }
+        if !self.conflicts.is_empty() {
+            println!("these settings were changed on both machines since they were last merged, the newer change was kept:");
+            for conflict in &self.conflicts {
+                println!("{conflict}");
+            }
+        }
+    }
+}
+
+//full listens reached the end of the song, partial listens were interrupted by a skip or by closing the player
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub struct ListenTime {
+    pub full_listens: u32,
+    pub full_seconds: u64,
+    pub partial_listens: u32,
+    pub partial_seconds: u64,
+}
+
+impl ListenTime {
+    pub fn listens(&self) -> u32 {
+        self.full_listens + self.partial_listens
+    }
+
+    pub fn total_seconds(&self) -> u64 {
+        self.full_seconds + self.partial_seconds
+    }
+}
+
+//like scrobblers do, a listen only counts as a play once one of the thresholds is reached, 0 disables a threshold
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct PlayThreshold {
+    pub percent: u32,
+    pub minutes: u32,
+}
+
+impl Default for PlayThreshold {
+    fn default() -> Self {
+        Self {
+            percent: 50,
+            minutes: 4,
+        }
+    }
+}
+
+impl PlayThreshold {
+    pub fn is_reached(self, listened: Duration, song_duration: Option<Duration>) -> bool {
+        if self.percent == 0 && self.minutes == 0 {
+            return true;
+        }
+        if let Some(song_duration) = song_duration {
+            if self.percent > 0
+                && listened.as_secs_f64() >= song_duration.as_secs_f64() * self.percent as f64 / 100.0
+            {
+                return true;
+            }
+        }
+        self.minutes > 0 && listened.as_secs() >= self.minutes as u64 * 60
+    }
+}
+
+impl Display for PlayThreshold {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.percent, self.minutes) {
+            (0, 0) => write!(f, "every started song counts as a play"),
+            (percent, 0) => write!(f, "a song counts as played after {percent}% of it"),
+            (0, minutes) => write!(f, "a song counts as played after {minutes} minutes"),
+            (percent, minutes) => write!(
+                f,
+                "a song counts as played after {percent}% of it or {minutes} minutes, whichever comes first"
+            ),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SkipStats {
+    pub count: u32,
+    //seconds into the song, only the most recent skips are kept
+    pub positions: Vec<u32>,
+}
+
+impl SkipStats {
+    pub fn average_position(&self) -> Option<u64> {
+        if self.positions.is_empty() {
+            return None;
+        }
+        let sum = self.positions.iter().map(|position| u64::from(*position)).sum::<u64>();
+        Some(sum / self.positions.len() as u64)
+    }
+}
+
+const MAX_RECORDED_SKIP_POSITIONS: usize = 20;
+//even a song that is always skipped can still be chosen
+const MIN_SKIP_MULTIPLIER: f64 = 0.05;
+
+//a value of 0 disables the constraint, explicitly chosen songs ignore all constraints
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub struct RotationConstraints {
+    pub min_songs_between: u32,
+    pub min_minutes_between: u32,
+    pub max_plays_per_day: u32,
+}
+
+impl RotationConstraints {
+    pub fn is_enabled(&self) -> bool {
+        self.min_songs_between > 0 || self.min_minutes_between > 0 || self.max_plays_per_day > 0
+    }
+}
+
+impl Display for RotationConstraints {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.is_enabled() {
+            return write!(f, "no rotation constraints");
+        }
+        let mut constraints = Vec::new();
+        if self.min_songs_between > 0 {
+            constraints.push(format!("no repeat within {} songs", self.min_songs_between));
+        }
+        if self.min_minutes_between > 0 {
+            constraints.push(format!(
+                "no repeat within {} minutes",
+                self.min_minutes_between
+            ));
+        }
+        if self.max_plays_per_day > 0 {
+            constraints.push(format!("at most {} plays per day", self.max_plays_per_day));
+        }
+        write!(f, "{}", constraints.join(", "))
+    }
+}
+
+//how the selection weight of a song grows with the number of songs played since it was last played
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub enum ProbabilityGrowth {
+    #[default]
+    Linear,
+    Logarithmic,
+    Capped(u32),
+    //the weight grows by 1 for every given number of hours since the song was last played
+    Time(f64),
+}
+
+impl ProbabilityGrowth {
+    pub fn get_weight(self, songs_played_since: u32, last_played: Option<i64>, now: i64) -> f64 {
+        //a song that was just played is never chosen again right away
+        if songs_played_since == 0 {
+            return 0.0;
+        }
+        match self {
+            ProbabilityGrowth::Linear => songs_played_since as f64,
+            ProbabilityGrowth::Logarithmic => 1.0 + (songs_played_since as f64).ln(),
+            ProbabilityGrowth::Capped(cap) => songs_played_since.min(cap.max(1)) as f64,
+            ProbabilityGrowth::Time(hours) => match last_played {
+                Some(last_played) => {
+                    let hours_since = (now - last_played).max(0) as f64 / 3600.0;
+                    1.0 + hours_since / hours
+                }
+                None => songs_played_since as f64,
+            },
+        }
+    }
+}
+
+impl Display for ProbabilityGrowth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProbabilityGrowth::Linear => write!(f, "linear"),
+            ProbabilityGrowth::Logarithmic => write!(f, "logarithmic"),
+            ProbabilityGrowth::Capped(cap) => write!(f, "capped at {cap}"),
+            ProbabilityGrowth::Time(hours) => write!(f, "+1 every {hours} hours since the last play"),
+        }
+    }
+}
+
+pub fn new_machine_id() -> String {
+    format!("{:016x}", rand::random::<u64>())
+}
+
+fn default_scrobble_log() -> bool {
+    true
+}
+
+fn default_smart_playlists() -> Vec<(String, String)> {
+    vec![
+        (String::from("Unheard"), String::from("playcount = 0")),
+        (
+            String::from("Focus favourites"),
+            String::from("starred and not has_lyrics"),
+        ),
+    ]
+}
+
+//the weights are relative to each other, an unrated song has the default rating and a starred song used to be chosen twice as often
+fn default_rating_weights() -> [u32; MAX_RATING as usize + 1] {
+    [1, 2, 3, 4, 6, 8]
 }
 
 impl PersistentSettings {
@@ -94,6 +455,26 @@ impl PersistentSettings {
             .unwrap_or_default()
     }
 
+    pub fn set_song_settings(&mut self, song: String, mut settings: SongSettings) {
+        let mut previous_settings = SongSettings::default();
+        if let Some(index) = self
+            .song_settings
+            .iter()
+            .position(|(other, _)| &song == other)
+        {
+            previous_settings = self.song_settings.remove(index).1;
+        }
+        //the time of the last change of every setting decides which one is kept when settings files are merged
+        let now = Local::now().timestamp();
+        for setting in SyncedSetting::ALL {
+            if !settings.same_setting(&previous_settings, setting) {
+                settings.set_modified(setting, now);
+            }
+        }
+        self.song_settings.push((song, settings));
+        self.song_settings.sort_by_key(|(song, _)| song.clone());
+    }
+
     pub fn get_probability_distribution(&self, paths: &[PathBuf]) -> Vec<u32> {
         let mut probabilities = crate::get_default_distribution(paths.len());
         for (song, probability) in &self.song_probability_distribution {
@@ -120,6 +501,28 @@ impl PersistentSettings {
         }
     }
 
+    pub fn set_song_play_count(&mut self, song: &str, play_count: u32) {
+        match self.song_play_count.iter_mut().find(|(other, _)| song == other) {
+            Some((_, count)) => *count = play_count,
+            None => self.song_play_count.push((song.to_string(), play_count)),
+        }
+    }
+
+    //play counts used to be keyed by the path of the song instead of its name
+    fn migrate_path_play_counts(&mut self) {
+        let (path_play_counts, play_counts): (Vec<_>, Vec<_>) = std::mem::take(&mut self.song_play_count)
+            .into_iter()
+            .partition(|(song, _)| Path::new(song).extension().is_some_and(|extension| extension == "mp3"));
+        self.song_play_count = play_counts;
+        for (path, play_count) in path_play_counts {
+            let song = crate::get_song_name(Path::new(&path));
+            match self.song_play_count.iter_mut().find(|(other, _)| &song == other) {
+                Some((_, total_play_count)) => *total_play_count += play_count,
+                None => self.song_play_count.push((song, play_count)),
+            }
+        }
+    }
+
     pub fn accumulate_play_count(&mut self, song: &str) {
         let index = self.song_play_count.iter().position(|(other, _)| song == other);
         match index {
@@ -127,6 +530,605 @@ impl PersistentSettings {
             None => self.song_play_count.push((song.to_string(), 1)),
         }
     }
+
+    pub fn get_song_last_played(&self, song: &str) -> Option<i64> {
+        self.song_last_played
+            .iter()
+            .find(|(other, _)| song == other)
+            .map(|(_, timestamp)| *timestamp)
+    }
+
+    pub fn set_song_last_played(&mut self, song: &str, timestamp: i64) {
+        match self.song_last_played.iter_mut().find(|(other, _)| song == other) {
+            Some((_, last_played)) => *last_played = timestamp,
+            None => self.song_last_played.push((song.to_string(), timestamp)),
+        }
+    }
+
+    pub fn record_play(&mut self, song: &str, timestamp: i64) {
+        self.set_song_last_played(song, timestamp);
+        //recent plays are only kept as long as the rotation constraints need them
+        self.recent_plays.push((song.to_string(), timestamp));
+        let start_of_day = utils::get_start_of_day(timestamp);
+        let kept_songs = self.rotation.min_songs_between as usize;
+        let play_count = self.recent_plays.len();
+        let mut i = 0;
+        self.recent_plays.retain(|(_, played)| {
+            i += 1;
+            *played >= start_of_day || play_count - i < kept_songs
+        });
+    }
+
+    pub fn violates_rotation(&self, song: &str, now: i64) -> bool {
+        let rotation = self.rotation;
+        if rotation.min_songs_between > 0
+            && self
+                .recent_plays
+                .iter()
+                .rev()
+                .take(rotation.min_songs_between as usize)
+                .any(|(other, _)| song == other)
+        {
+            return true;
+        }
+        if rotation.min_minutes_between > 0 {
+            if let Some(last_played) = self.get_song_last_played(song) {
+                if now - last_played < rotation.min_minutes_between as i64 * 60 {
+                    return true;
+                }
+            }
+        }
+        if rotation.max_plays_per_day > 0 {
+            let start_of_day = utils::get_start_of_day(now);
+            let plays_today = self
+                .recent_plays
+                .iter()
+                .filter(|(other, played)| song == other && *played >= start_of_day)
+                .count();
+            if plays_today >= rotation.max_plays_per_day as usize {
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn get_skip_stats(&self, song: &str) -> SkipStats {
+        self.song_skips
+            .iter()
+            .find(|(other, _)| song == other)
+            .map(|(_, skips)| skips.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn record_skip(&mut self, song: &str, position: u32) {
+        if !self.song_skips.iter().any(|(other, _)| song == other) {
+            self.song_skips.push((song.to_string(), SkipStats::default()));
+        }
+        let (_, skips) = self
+            .song_skips
+            .iter_mut()
+            .find(|(other, _)| song == other)
+            .expect("skip stats were just added");
+        skips.count += 1;
+        skips.positions.push(position);
+        if skips.positions.len() > MAX_RECORDED_SKIP_POSITIONS {
+            skips.positions.remove(0);
+        }
+    }
+
+    //a skip rate of 1 means the song is skipped every time it starts
+    pub fn get_skip_rate(&self, song: &str) -> f64 {
+        let skips = self.get_skip_stats(song).count;
+        //settings from before listens were recorded only know the play count
+        let listens = self
+            .get_listen_time(song)
+            .listens()
+            .max(self.get_song_play_count(song))
+            .max(skips)
+            .max(1);
+        skips as f64 / listens as f64
+    }
+
+    pub fn get_listen_time(&self, song: &str) -> ListenTime {
+        self.song_listen_time
+            .iter()
+            .find(|(other, _)| song == other)
+            .map(|(_, listen_time)| *listen_time)
+            .unwrap_or_default()
+    }
+
+    pub fn record_listen(&mut self, song: &str, listened: Duration, completed: bool) {
+        if !self.song_listen_time.iter().any(|(other, _)| song == other) {
+            self.song_listen_time.push((song.to_string(), ListenTime::default()));
+        }
+        let (_, listen_time) = self
+            .song_listen_time
+            .iter_mut()
+            .find(|(other, _)| song == other)
+            .expect("listen time was just added");
+        if completed {
+            listen_time.full_listens += 1;
+            listen_time.full_seconds += listened.as_secs();
+        } else {
+            listen_time.partial_listens += 1;
+            listen_time.partial_seconds += listened.as_secs();
+        }
+    }
+
+    pub fn get_song_position(&self, song: &str) -> Option<Duration> {
+        self.song_positions
+            .iter()
+            .find(|(other, _)| song == other)
+            .map(|(_, position)| Duration::from_secs(*position))
+    }
+
+    pub fn set_song_position(&mut self, song: &str, position: Option<Duration>) {
+        self.song_positions.retain(|(other, _)| song != other);
+        if let Some(position) = position.filter(|position| position.as_secs() > 0) {
+            self.song_positions.push((song.to_string(), position.as_secs()));
+        }
+    }
+
+    pub fn get_bookmarks(&self, song: &str) -> &[(String, u64)] {
+        self.song_bookmarks
+            .iter()
+            .find(|(other, _)| song == other)
+            .map_or(&[], |(_, bookmarks)| bookmarks.as_slice())
+    }
+
+    pub fn get_bookmark(&self, song: &str, name: &str) -> Option<Duration> {
+        self.get_bookmarks(song)
+            .iter()
+            .find(|(other, _)| same_name(name, other))
+            .map(|(_, position)| Duration::from_secs(*position))
+    }
+
+    //an existing bookmark with the same name is moved to the new position
+    pub fn set_bookmark(&mut self, song: &str, name: &str, position: Duration) {
+        if !self.song_bookmarks.iter().any(|(other, _)| song == other) {
+            self.song_bookmarks.push((song.to_string(), Vec::new()));
+        }
+        let (_, bookmarks) = self
+            .song_bookmarks
+            .iter_mut()
+            .find(|(other, _)| song == other)
+            .expect("bookmarks were just added");
+        bookmarks.retain(|(other, _)| !same_name(name, other));
+        bookmarks.push((name.to_string(), position.as_secs()));
+        bookmarks.sort_by_key(|(_, position)| *position);
+    }
+
+    pub fn remove_bookmark(&mut self, song: &str, name: &str) -> bool {
+        let Some((_, bookmarks)) = self.song_bookmarks.iter_mut().find(|(other, _)| song == other) else {
+            return false;
+        };
+        let bookmark_count = bookmarks.len();
+        bookmarks.retain(|(other, _)| !same_name(name, other));
+        let removed = bookmarks.len() != bookmark_count;
+        self.song_bookmarks.retain(|(_, bookmarks)| !bookmarks.is_empty());
+        removed
+    }
+
+    //bookmarks and resume positions are relative to the start trim point, so they move when it changes
+    pub fn move_song_start(&mut self, song: &str, old_start: Duration, new_start: Duration) {
+        let move_position = |position: u64| (position + old_start.as_secs()).checked_sub(new_start.as_secs());
+        if let Some((_, bookmarks)) = self.song_bookmarks.iter_mut().find(|(other, _)| song == other) {
+            *bookmarks = bookmarks
+                .drain(..)
+                .filter_map(|(name, position)| Some((name, move_position(position)?)))
+                .collect();
+        }
+        self.song_bookmarks.retain(|(_, bookmarks)| !bookmarks.is_empty());
+        if let Some(position) = self.get_song_position(song) {
+            let position = move_position(position.as_secs()).map(Duration::from_secs);
+            self.set_song_position(song, position);
+        }
+    }
+
+    pub fn get_skip_multiplier(&self, song: &str) -> f64 {
+        let penalty = self.skip_penalty.min(100) as f64 / 100.0;
+        (1.0 - penalty * self.get_skip_rate(song)).max(MIN_SKIP_MULTIPLIER)
+    }
+
+    pub fn get_rating_weight(&self, rating: u8) -> u32 {
+        self.rating_weights[rating.min(MAX_RATING) as usize]
+    }
+
+    pub fn set_rating_weight(&mut self, rating: u8, weight: u32) {
+        self.rating_weights[rating.min(MAX_RATING) as usize] = weight;
+    }
+
+    pub fn get_smart_playlists(&self) -> &[(String, String)] {
+        &self.smart_playlists
+    }
+
+    pub fn find_smart_playlist(&self, name: &str) -> Option<(String, String)> {
+        self.smart_playlists
+            .iter()
+            .find(|(other, _)| same_name(name, other))
+            .cloned()
+    }
+
+    pub fn set_smart_playlist(&mut self, name: &str, filter: &str) {
+        self.remove_smart_playlist(name);
+        self.smart_playlists
+            .push((name.to_string(), filter.to_string()));
+    }
+
+    pub fn remove_smart_playlist(&mut self, name: &str) -> bool {
+        let playlist_count = self.smart_playlists.len();
+        self.smart_playlists
+            .retain(|(other, _)| !same_name(name, other));
+        self.smart_playlists.len() != playlist_count
+    }
+
+    pub fn get_playlists(&self) -> &[(String, Vec<String>)] {
+        &self.playlists
+    }
+
+    pub fn find_playlist(&self, name: &str) -> Option<&(String, Vec<String>)> {
+        self.playlists.iter().find(|(other, _)| same_name(name, other))
+    }
+
+    pub fn find_playlist_mut(&mut self, name: &str) -> Option<&mut (String, Vec<String>)> {
+        self.playlists
+            .iter_mut()
+            .find(|(other, _)| same_name(name, other))
+    }
+
+    pub fn create_playlist(&mut self, name: &str) -> bool {
+        if self.find_playlist(name).is_some() {
+            return false;
+        }
+        self.playlists.push((name.to_string(), Vec::new()));
+        true
+    }
+
+    pub fn remove_playlist(&mut self, name: &str) -> bool {
+        let playlist_count = self.playlists.len();
+        self.playlists.retain(|(other, _)| !same_name(name, other));
+        self.playlists.len() != playlist_count
+    }
+
+    //play counts add up, the newer change of every setting of a song wins and probability weights take the maximum
+    pub fn merge(&mut self, other: &PersistentSettings) -> Result<MergeReport, String> {
+        if other.machine_id.is_empty() {
+            return Err(String::from(
+                "these settings have no machine id yet, run the new version of the player once on the other machine before merging",
+            ));
+        }
+        if other.machine_id == self.machine_id {
+            return Err(String::from(
+                "these settings have the machine id of this machine, if they were copied from this machine run 'new machine id' on the other machine first",
+            ));
+        }
+        let mut report = MergeReport::default();
+        let now = Local::now().timestamp();
+        let last_merged = self
+            .merged_machines
+            .iter()
+            .find(|machine| machine.machine_id == other.machine_id)
+            .map_or(0, |machine| machine.last_merged);
+
+        //the plays of the other machine itself and the plays it merged from further machines
+        let mut machines = vec![MergedMachine {
+            machine_id: other.machine_id.clone(),
+            last_merged: now,
+            play_counts: other
+                .song_play_count
+                .iter()
+                .map(|(song, play_count)| (song.clone(), play_count.saturating_sub(other.get_merged_play_count(song))))
+                .collect(),
+        }];
+        machines.extend(
+            other
+                .merged_machines
+                .iter()
+                .filter(|machine| machine.machine_id != self.machine_id)
+                .cloned(),
+        );
+        for machine in machines {
+            if !self
+                .merged_machines
+                .iter()
+                .any(|other| other.machine_id == machine.machine_id)
+            {
+                self.merged_machines.push(MergedMachine {
+                    machine_id: machine.machine_id.clone(),
+                    last_merged: 0,
+                    play_counts: Vec::new(),
+                });
+            }
+            let index = self
+                .merged_machines
+                .iter()
+                .position(|other| other.machine_id == machine.machine_id)
+                .expect("the machine was just added");
+            for (song, play_count) in &machine.play_counts {
+                let known_play_count = self.merged_machines[index].get_play_count(song);
+                if *play_count <= known_play_count {
+                    continue;
+                }
+                let added_plays = play_count - known_play_count;
+                let total_play_count = self.get_song_play_count(song) + added_plays;
+                self.set_song_play_count(song, total_play_count);
+                report.added_plays += added_plays;
+                let merged_machine = &mut self.merged_machines[index];
+                match merged_machine.play_counts.iter_mut().find(|(other, _)| song == other) {
+                    Some((_, known_play_count)) => *known_play_count = *play_count,
+                    None => merged_machine.play_counts.push((song.clone(), *play_count)),
+                }
+            }
+            if machine.machine_id == other.machine_id {
+                self.merged_machines[index].last_merged = now;
+            }
+        }
+
+        for (song, other_settings) in &other.song_settings {
+            let own_settings = self.get_song_settings(song);
+            let has_own_settings = self.song_settings.iter().any(|(other, _)| song == other);
+            let mut merged_settings = own_settings.clone();
+            let mut taken_settings = Vec::new();
+            for setting in SyncedSetting::ALL {
+                if own_settings.same_setting(other_settings, setting) {
+                    continue;
+                }
+                let own_modified = own_settings.get_modified(setting);
+                let other_modified = other_settings.get_modified(setting);
+                let conflict = format!(
+                    "{song} {setting}: {} here, {} on the other machine",
+                    own_settings.describe_setting(setting),
+                    other_settings.describe_setting(setting)
+                );
+                //settings from before changes were stamped can't tell which side is newer
+                if has_own_settings && own_modified == 0 && other_modified == 0 {
+                    report.conflicts.push(format!("{conflict}, the setting of this machine was kept"));
+                    continue;
+                }
+                if own_modified > last_merged && other_modified > last_merged {
+                    report.conflicts.push(conflict);
+                }
+                if other_modified > own_modified || !has_own_settings {
+                    merged_settings.take_setting(other_settings, setting);
+                    taken_settings.push(setting.to_string());
+                }
+            }
+            if !taken_settings.is_empty() {
+                //set_song_settings would stamp the settings with the time of the merge
+                self.song_settings.retain(|(other, _)| song != other);
+                self.song_settings.push((song.clone(), merged_settings));
+                report.taken_song_settings.push(format!("{song} ({})", taken_settings.join(", ")));
+            }
+        }
+        self.song_settings.sort_by_key(|(song, _)| song.clone());
+
+        for (song, probability) in &other.song_probability_distribution {
+            match self
+                .song_probability_distribution
+                .iter_mut()
+                .find(|(other, _)| song == other)
+            {
+                Some((_, own_probability)) => *own_probability = (*own_probability).max(*probability),
+                None => self.song_probability_distribution.push((song.clone(), *probability)),
+            }
+        }
+        for (song, last_played) in &other.song_last_played {
+            if self.get_song_last_played(song).is_none_or(|own| *last_played > own) {
+                self.set_song_last_played(song, *last_played);
+            }
+        }
+        for (name, songs) in &other.playlists {
+            match self.find_playlist(name) {
+                None => {
+                    self.playlists.push((name.clone(), songs.clone()));
+                    report.added_playlists.push(name.clone());
+                }
+                Some((_, own_songs)) if own_songs != songs => {
+                    report.conflicts.push(format!("playlist {name}: the songs differ, the playlist of this machine was kept"));
+                }
+                Some(_) => (),
+            }
+        }
+        for (name, filter) in &other.smart_playlists {
+            match self.find_smart_playlist(name) {
+                None => {
+                    self.smart_playlists.push((name.clone(), filter.clone()));
+                    report.added_playlists.push(name.clone());
+                }
+                Some((_, own_filter)) if &own_filter != filter => {
+                    report.conflicts.push(format!(
+                        "smart playlist {name}: \"{own_filter}\" here, \"{filter}\" on the other machine, the filter of this machine was kept"
+                    ));
+                }
+                Some(_) => (),
+            }
+        }
+        Ok(report)
+    }
+
+    pub fn get_machine_id(&self) -> &str {
+        &self.machine_id
+    }
+
+    //a settings file that was copied to another machine needs an id of its own there,
+    //the plays so far count as merged from the previous id so they are never added twice
+    pub fn set_machine_id(&mut self, machine_id: String) {
+        let own_play_counts = self
+            .song_play_count
+            .iter()
+            .map(|(song, play_count)| (song.clone(), play_count.saturating_sub(self.get_merged_play_count(song))))
+            .filter(|(_, play_count)| *play_count > 0)
+            .collect();
+        let old_machine_id = std::mem::replace(&mut self.machine_id, machine_id);
+        if !old_machine_id.is_empty() {
+            self.merged_machines.push(MergedMachine {
+                machine_id: old_machine_id,
+                last_merged: Local::now().timestamp(),
+                play_counts: own_play_counts,
+            });
+        }
+    }
+
+    fn has_own_plays(&self) -> bool {
+        self.song_play_count
+            .iter()
+            .any(|(song, play_count)| *play_count > self.get_merged_play_count(song))
+    }
+
+    //adds the plays of the song since the library file was last imported and returns how many were added
+    pub fn import_play_count(&mut self, library: &str, song: &str, play_count: u32) -> u32 {
+        if !self
+            .imported_libraries
+            .iter()
+            .any(|imported_library| imported_library.file == library)
+        {
+            self.imported_libraries.push(ImportedLibrary {
+                file: library.to_string(),
+                play_counts: Vec::new(),
+            });
+        }
+        let imported_library = self
+            .imported_libraries
+            .iter_mut()
+            .find(|imported_library| imported_library.file == library)
+            .expect("the library was just added");
+        let last_play_count = imported_library
+            .play_counts
+            .iter()
+            .find(|(other, _)| song == other)
+            .map_or(0, |(_, last_play_count)| *last_play_count);
+        imported_library.play_counts.retain(|(other, _)| song != other);
+        imported_library.play_counts.push((song.to_string(), play_count));
+        //the play count of the other player might have been reset, then its new plays are added from there on
+        let added_plays = play_count.saturating_sub(last_play_count);
+        let total_play_count = self.get_song_play_count(song) + added_plays;
+        self.set_song_play_count(song, total_play_count);
+        added_plays
+    }
+
+    fn get_merged_play_count(&self, song: &str) -> u32 {
+        self.merged_machines
+            .iter()
+            .map(|machine| machine.get_play_count(song))
+            .sum()
+    }
+
+    pub fn get_song_keys(&self) -> Vec<String> {
+        let mut keys = Vec::new();
+        let settings_keys = self.song_settings.iter().map(|(song, _)| song);
+        let probability_keys = self.song_probability_distribution.iter().map(|(song, _)| song);
+        let play_count_keys = self.song_play_count.iter().map(|(song, _)| song);
+        let last_played_keys = self.song_last_played.iter().map(|(song, _)| song);
+        let skip_keys = self.song_skips.iter().map(|(song, _)| song);
+        let listen_time_keys = self.song_listen_time.iter().map(|(song, _)| song);
+        let position_keys = self.song_positions.iter().map(|(song, _)| song);
+        let bookmark_keys = self.song_bookmarks.iter().map(|(song, _)| song);
+        for key in settings_keys
+            .chain(probability_keys)
+            .chain(play_count_keys)
+            .chain(last_played_keys)
+            .chain(skip_keys)
+            .chain(listen_time_keys)
+            .chain(position_keys)
+            .chain(bookmark_keys)
+        {
+            if !keys.contains(key) {
+                keys.push(key.clone());
+            }
+        }
+        keys
+    }
+
+    pub fn remove_song(&mut self, song: &str) {
+        self.song_settings.retain(|(other, _)| song != other);
+        self.song_probability_distribution
+            .retain(|(other, _)| song != other);
+        self.song_play_count.retain(|(other, _)| song != other);
+        self.song_last_played.retain(|(other, _)| song != other);
+        self.recent_plays.retain(|(other, _)| song != other);
+        self.song_skips.retain(|(other, _)| song != other);
+        self.song_listen_time.retain(|(other, _)| song != other);
+        self.song_positions.retain(|(other, _)| song != other);
+        self.song_bookmarks.retain(|(other, _)| song != other);
+        for machine in &mut self.merged_machines {
+            machine.play_counts.retain(|(other, _)| song != other);
+        }
+        for library in &mut self.imported_libraries {
+            library.play_counts.retain(|(other, _)| song != other);
+        }
+    }
+
+    pub fn rename_song(&mut self, old_song: &str, new_song: &str) {
+        for (song, _) in &mut self.song_settings {
+            if song == old_song {
+                *song = new_song.to_string();
+            }
+        }
+        for (song, _) in &mut self.song_probability_distribution {
+            if song == old_song {
+                *song = new_song.to_string();
+            }
+        }
+        for (song, _) in &mut self.song_play_count {
+            if song == old_song {
+                *song = new_song.to_string();
+            }
+        }
+        for (song, _) in &mut self.song_last_played {
+            if song == old_song {
+                *song = new_song.to_string();
+            }
+        }
+        for (song, _) in &mut self.recent_plays {
+            if song == old_song {
+                *song = new_song.to_string();
+            }
+        }
+        for (song, _) in &mut self.song_skips {
+            if song == old_song {
+                *song = new_song.to_string();
+            }
+        }
+        for (song, _) in &mut self.song_listen_time {
+            if song == old_song {
+                *song = new_song.to_string();
+            }
+        }
+        for (song, _) in &mut self.song_positions {
+            if song == old_song {
+                *song = new_song.to_string();
+            }
+        }
+        for (song, _) in &mut self.song_bookmarks {
+            if song == old_song {
+                *song = new_song.to_string();
+            }
+        }
+        for song in self.playlists.iter_mut().flat_map(|(_, songs)| songs) {
+            if song == old_song {
+                *song = new_song.to_string();
+            }
+        }
+        for (song, _) in self
+            .merged_machines
+            .iter_mut()
+            .flat_map(|machine| &mut machine.play_counts)
+        {
+            if song == old_song {
+                *song = new_song.to_string();
+            }
+        }
+        for (song, _) in self
+            .imported_libraries
+            .iter_mut()
+            .flat_map(|library| &mut library.play_counts)
+        {
+            if song == old_song {
+                *song = new_song.to_string();
+            }
+        }
+        self.song_settings.sort_by_key(|(song, _)| song.clone());
+    }
 }
 
 impl Default for PersistentSettings {
@@ -136,40 +1138,255 @@ impl Default for PersistentSettings {
             song_settings: Vec::new(),
             song_probability_distribution: Vec::new(),
             song_play_count: Vec::new(),
+            rating_weights: default_rating_weights(),
+            smart_playlists: default_smart_playlists(),
+            playlists: Vec::new(),
+            song_last_played: Vec::new(),
+            probability_growth: ProbabilityGrowth::default(),
+            rotation: RotationConstraints::default(),
+            recent_plays: Vec::new(),
+            song_skips: Vec::new(),
+            skip_penalty: 0,
+            song_listen_time: Vec::new(),
+            play_threshold: PlayThreshold::default(),
+            scrobble_log: default_scrobble_log(),
+            machine_id: new_machine_id(),
+            merged_machines: Vec::new(),
+            imported_libraries: Vec::new(),
+            song_positions: Vec::new(),
+            song_bookmarks: Vec::new(),
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+pub const DISLIKE_RATING: u8 = 0;
+pub const DEFAULT_RATING: u8 = 3;
+pub const MAX_RATING: u8 = 5;
+
+pub const LYRICS_TAG: &str = "lyrics";
+
+//the settings of a song that are merged one by one, whether a file is broken stays with the machine it failed on
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum SyncedSetting {
+    Volume,
+    Rating,
+    Tags,
+    ResumePosition,
+    Trim,
+}
+
+impl SyncedSetting {
+    const ALL: [SyncedSetting; 5] = [
+        SyncedSetting::Volume,
+        SyncedSetting::Rating,
+        SyncedSetting::Tags,
+        SyncedSetting::ResumePosition,
+        SyncedSetting::Trim,
+    ];
+}
+
+impl Display for SyncedSetting {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncedSetting::Volume => write!(f, "volume"),
+            SyncedSetting::Rating => write!(f, "rating"),
+            SyncedSetting::Tags => write!(f, "tags"),
+            SyncedSetting::ResumePosition => write!(f, "resume position"),
+            SyncedSetting::Trim => write!(f, "trim points"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct SongSettings {
     pub song_volume: f32,
-    pub starred: bool,
-    pub has_lyrics: bool,
+    #[serde(default)]
+    pub rating: Option<u8>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    pub is_broken: bool,
+    //songs used to only be starred or not, a starred song is migrated to the highest rating
+    #[serde(rename = "starred", default, skip_serializing)]
+    legacy_starred: bool,
+    //having lyrics used to be a flag of its own, it is migrated to the built-in lyrics tag
+    #[serde(rename = "has_lyrics", default, skip_serializing)]
+    legacy_has_lyrics: bool,
+    //settings used to only have the time of their last change, it is migrated to every setting
+    #[serde(rename = "modified", default, skip_serializing)]
+    legacy_modified: i64,
+    //when each setting was last changed, the newer change of every setting is kept when settings files are merged
+    #[serde(default)]
+    settings_modified: Vec<(SyncedSetting, i64)>,
+    //long files like dj sets and audiobook chapters continue where they were stopped
+    #[serde(default)]
+    pub resume_position: bool,
+    //silent intros and outros are cut off at these positions in seconds
+    #[serde(default)]
+    pub trim_start: Option<u64>,
+    #[serde(default)]
+    pub trim_end: Option<u64>,
+}
+
+impl SongSettings {
+    pub fn get_rating(&self) -> u8 {
+        self.rating.unwrap_or(DEFAULT_RATING)
+    }
+
+    pub fn is_starred(&self) -> bool {
+        self.get_rating() == MAX_RATING
+    }
+
+    pub fn has_lyrics(&self) -> bool {
+        self.has_tag(LYRICS_TAG)
+    }
+
+    pub fn get_tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|other| other == tag)
+    }
+
+    pub fn add_tag(&mut self, tag: &str) -> bool {
+        if self.has_tag(tag) {
+            return false;
+        }
+        self.tags.push(tag.to_string());
+        self.tags.sort();
+        true
+    }
+
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let tag_count = self.tags.len();
+        self.tags.retain(|other| other != tag);
+        self.tags.len() != tag_count
+    }
+
+    pub fn get_trim_start(&self) -> Duration {
+        Duration::from_secs(self.trim_start.unwrap_or_default())
+    }
+
+    pub fn get_trim_end(&self) -> Option<Duration> {
+        self.trim_end.map(Duration::from_secs)
+    }
+
+    //compares the settings that are merged, so neither the time they were changed nor a broken file counts
+    pub fn same_as(&self, other: &SongSettings) -> bool {
+        SyncedSetting::ALL
+            .iter()
+            .all(|setting| self.same_setting(other, *setting))
+    }
+
+    fn same_setting(&self, other: &SongSettings, setting: SyncedSetting) -> bool {
+        match setting {
+            SyncedSetting::Volume => (self.song_volume - other.song_volume).abs() <= f32::EPSILON,
+            SyncedSetting::Rating => self.rating == other.rating,
+            SyncedSetting::Tags => self.tags == other.tags,
+            SyncedSetting::ResumePosition => self.resume_position == other.resume_position,
+            SyncedSetting::Trim => self.trim_start == other.trim_start && self.trim_end == other.trim_end,
+        }
+    }
+
+    //takes the value of the setting together with the time it was changed
+    fn take_setting(&mut self, other: &SongSettings, setting: SyncedSetting) {
+        match setting {
+            SyncedSetting::Volume => self.song_volume = other.song_volume,
+            SyncedSetting::Rating => self.rating = other.rating,
+            SyncedSetting::Tags => self.tags.clone_from(&other.tags),
+            SyncedSetting::ResumePosition => self.resume_position = other.resume_position,
+            SyncedSetting::Trim => {
+                self.trim_start = other.trim_start;
+                self.trim_end = other.trim_end;
+            }
+        }
+        self.set_modified(setting, other.get_modified(setting));
+    }
+
+    pub fn describe_setting(&self, setting: SyncedSetting) -> String {
+        let format_seconds = |seconds: u64| utils::format_duration(&Duration::from_secs(seconds));
+        match setting {
+            SyncedSetting::Volume => format!("{}%", (self.song_volume * 100.0).round()),
+            SyncedSetting::Rating => self.rating.map_or(String::from("unrated"), format_rating),
+            SyncedSetting::Tags if self.tags.is_empty() => String::from("no tags"),
+            SyncedSetting::Tags => self.tags.join(", "),
+            SyncedSetting::ResumePosition => self.resume_position.to_string(),
+            SyncedSetting::Trim => format!(
+                "{} - {}",
+                format_seconds(self.trim_start.unwrap_or_default()),
+                self.trim_end.map_or(String::from("the end"), format_seconds)
+            ),
+        }
+    }
+
+    //0 means the setting was never changed since changes are stamped
+    pub fn get_modified(&self, setting: SyncedSetting) -> i64 {
+        self.settings_modified
+            .iter()
+            .find(|(other, _)| *other == setting)
+            .map_or(0, |(_, modified)| *modified)
+    }
+
+    fn set_modified(&mut self, setting: SyncedSetting, modified: i64) {
+        self.settings_modified.retain(|(other, _)| *other != setting);
+        if modified != 0 {
+            self.settings_modified.push((setting, modified));
+        }
+    }
+
+    fn migrate_legacy_flags(&mut self) {
+        if self.legacy_modified != 0 && self.settings_modified.is_empty() {
+            for setting in SyncedSetting::ALL {
+                self.set_modified(setting, self.legacy_modified);
+            }
+        }
+        self.legacy_modified = 0;
+        if self.legacy_starred && self.rating.is_none() {
+            self.rating = Some(MAX_RATING);
+        }
+        self.legacy_starred = false;
+        if self.legacy_has_lyrics {
+            self.add_tag(LYRICS_TAG);
+        }
+        self.legacy_has_lyrics = false;
+    }
 }
 
 impl Default for SongSettings {
     fn default() -> Self {
         Self {
             song_volume: 0.5,
-            starred: false,
-            has_lyrics: false,
+            rating: None,
+            tags: Vec::new(),
+            is_broken: false,
+            legacy_starred: false,
+            legacy_has_lyrics: false,
+            legacy_modified: 0,
+            settings_modified: Vec::new(),
+            resume_position: false,
+            trim_start: None,
+            trim_end: None,
         }
     }
 }
 
+//names typed into the console are compared without spaces and case
+pub fn same_name(name: &str, other: &str) -> bool {
+    name.replace(' ', "").to_lowercase() == other.replace(' ', "").to_lowercase()
+}
+
+pub fn format_rating(rating: u8) -> String {
+    if rating == DISLIKE_RATING {
+        String::from("disliked")
+    } else {
+        format!("{}{}", "*".repeat(rating as usize), "-".repeat((MAX_RATING - rating) as usize))
+    }
+}
+
 pub fn update_song_settings(song: String, settings: SongSettings) {
     let mut persistent_settings = get_persistent_settings();
-    if let Some(index) = persistent_settings
-        .song_settings
-        .iter()
-        .position(|(other, _)| &song == other)
-    {
-        persistent_settings.song_settings.remove(index);
-    }
-    persistent_settings.song_settings.push((song, settings));
-    persistent_settings
-        .song_settings
-        .sort_by_key(|(song, _)| song.clone());
+    persistent_settings.set_song_settings(song, settings);
     update_settings(&persistent_settings);
 }
 
@@ -181,13 +1398,183 @@ pub fn to_json(settings: &PersistentSettings) -> String {
     serde_json::to_string(settings).expect("json conversion failed")
 }
 
+pub fn read_settings_file(file: &str) -> Result<PersistentSettings, String> {
+    let json = fs::read_to_string(file).map_err(|error| format!("failed to read {file} ({error})"))?;
+    parse_settings(&json).map_err(|error| format!("{file} is not a valid settings file ({error})"))
+}
+
 pub fn get_persistent_settings() -> PersistentSettings {
     let file_path = "playlist-settings.json";
     let playlist_settings =
         fs::read_to_string(file_path).expect("Failed to read playlist-settings file");
-    from_json(&playlist_settings)
+    let mut settings = from_json(&playlist_settings);
+    if settings.machine_id.is_empty() {
+        settings.machine_id = new_machine_id();
+        update_settings(&settings);
+    }
+    settings
+}
+
+//the id of this machine is also kept in the home directory, so settings that were copied from another machine are recognized
+fn get_machine_id_file() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(".music-player-machine-id"))
+}
+
+pub fn save_machine_id(machine_id: &str) {
+    let Some(file) = get_machine_id_file() else {
+        return;
+    };
+    if let Err(error) = fs::write(&file, machine_id) {
+        println!("Failed to save the id of this machine to {} ({error})", file.display());
+    }
+}
+
+//settings that were copied from another machine get an id of their own before anything is played on this machine,
+//so only the plays from before the copy count as plays of the other machine
+pub fn check_machine_id() {
+    let Some(file) = get_machine_id_file() else {
+        return;
+    };
+    let mut settings = get_persistent_settings();
+    let machine_id = fs::read_to_string(file)
+        .map(|machine_id| machine_id.trim().to_string())
+        .unwrap_or_default();
+    if machine_id == settings.machine_id {
+        return;
+    }
+    //without a saved id the settings are only known to be from this machine if nothing was played with them yet
+    if machine_id.is_empty() && !settings.has_own_plays() {
+        save_machine_id(&settings.machine_id);
+        return;
+    }
+    let machine_id = if machine_id.is_empty() {
+        let machine_id = new_machine_id();
+        save_machine_id(&machine_id);
+        println!(
+            "this machine now has the id {machine_id}, the plays so far count as plays of the previous id {}",
+            settings.machine_id
+        );
+        machine_id
+    } else {
+        println!(
+            "the settings were copied from the machine with the id {}, this machine continues with its own id {machine_id}",
+            settings.machine_id
+        );
+        machine_id
+    };
+    settings.set_machine_id(machine_id);
+    update_settings(&settings);
 }
 
 fn from_json(json_str: &str) -> PersistentSettings {
-    serde_json::from_str(json_str).expect("invalid json playlist-settings file")
+    parse_settings(json_str).expect("invalid json playlist-settings file")
+}
+
+fn parse_settings(json_str: &str) -> Result<PersistentSettings, serde_json::Error> {
+    let mut settings: PersistentSettings = serde_json::from_str(json_str)?;
+    for (_, song_settings) in &mut settings.song_settings {
+        song_settings.migrate_legacy_flags();
+    }
+    settings.migrate_path_play_counts();
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_json, new_machine_id, to_json, PersistentSettings, SongSettings, SyncedSetting};
+
+    fn copy(settings: &PersistentSettings) -> PersistentSettings {
+        from_json(&to_json(settings))
+    }
+
+    #[test]
+    fn importing_a_library_again_only_adds_its_new_plays() {
+        let mut settings = PersistentSettings::default();
+        settings.set_song_play_count("Song", 2);
+        assert_eq!(settings.import_play_count("/music/Library.xml", "Song", 5), 5);
+        assert_eq!(settings.import_play_count("/music/Library.xml", "Song", 5), 0);
+        assert_eq!(settings.get_song_play_count("Song"), 7);
+        assert_eq!(settings.import_play_count("/music/Library.xml", "Song", 6), 1);
+        assert_eq!(settings.import_play_count("/music/rhythmdb.xml", "Song", 1), 1);
+        assert_eq!(settings.get_song_play_count("Song"), 9);
+    }
+
+    #[test]
+    fn merging_again_never_counts_a_play_twice() {
+        let mut laptop = PersistentSettings::default();
+        let mut desktop = PersistentSettings::default();
+        laptop.set_song_play_count("Song", 3);
+        desktop.set_song_play_count("Song", 2);
+        assert_eq!(laptop.merge(&desktop).unwrap().added_plays, 2);
+        assert_eq!(laptop.merge(&desktop).unwrap().added_plays, 0);
+        desktop.accumulate_play_count("Song");
+        assert_eq!(laptop.merge(&desktop).unwrap().added_plays, 1);
+        assert_eq!(laptop.get_song_play_count("Song"), 6);
+    }
+
+    #[test]
+    fn merging_in_both_directions_counts_every_play_once() {
+        let mut laptop = PersistentSettings::default();
+        let mut desktop = PersistentSettings::default();
+        laptop.set_song_play_count("Song", 3);
+        desktop.set_song_play_count("Song", 2);
+        laptop.merge(&desktop).unwrap();
+        assert_eq!(desktop.merge(&laptop).unwrap().added_plays, 3);
+        assert_eq!(laptop.merge(&desktop).unwrap().added_plays, 0);
+        laptop.accumulate_play_count("Song");
+        assert_eq!(desktop.merge(&laptop).unwrap().added_plays, 1);
+        assert_eq!(laptop.get_song_play_count("Song"), 6);
+        assert_eq!(desktop.get_song_play_count("Song"), 6);
+    }
+
+    #[test]
+    fn copied_settings_only_count_the_plays_before_the_copy_once() {
+        let mut laptop = PersistentSettings::default();
+        laptop.set_song_play_count("Song", 4);
+        let mut desktop = copy(&laptop);
+        desktop.set_machine_id(new_machine_id());
+        desktop.accumulate_play_count("Song");
+        laptop.accumulate_play_count("Song");
+        assert_eq!(laptop.merge(&desktop).unwrap().added_plays, 1);
+        assert_eq!(desktop.merge(&laptop).unwrap().added_plays, 1);
+        assert_eq!(laptop.get_song_play_count("Song"), 6);
+        assert_eq!(desktop.get_song_play_count("Song"), 6);
+    }
+
+    #[test]
+    fn changes_to_different_settings_of_a_song_are_all_kept() {
+        let mut laptop_settings = SongSettings {
+            rating: Some(4),
+            ..SongSettings::default()
+        };
+        laptop_settings.set_modified(SyncedSetting::Rating, 200);
+        let mut desktop_settings = SongSettings {
+            song_volume: 0.8,
+            ..SongSettings::default()
+        };
+        desktop_settings.set_modified(SyncedSetting::Volume, 300);
+        let mut laptop = PersistentSettings::default();
+        let mut desktop = PersistentSettings::default();
+        laptop.song_settings.push((String::from("Song"), laptop_settings));
+        desktop.song_settings.push((String::from("Song"), desktop_settings));
+        let report = laptop.merge(&desktop).unwrap();
+        assert!(report.conflicts.is_empty());
+        let merged_settings = laptop.get_song_settings("Song");
+        assert_eq!(merged_settings.rating, Some(4));
+        assert!((merged_settings.song_volume - 0.8).abs() <= f32::EPSILON);
+        assert_eq!(merged_settings.get_modified(SyncedSetting::Volume), 300);
+    }
+
+    #[test]
+    fn broken_songs_are_neither_stamped_nor_merged() {
+        let mut desktop = PersistentSettings::default();
+        let mut song_settings = desktop.get_song_settings("Song");
+        song_settings.is_broken = true;
+        desktop.set_song_settings(String::from("Song"), song_settings);
+        assert!(desktop.get_song_settings("Song").settings_modified.is_empty());
+        let mut laptop = PersistentSettings::default();
+        assert!(laptop.merge(&desktop).unwrap().taken_song_settings.is_empty());
+        assert!(!laptop.get_song_settings("Song").is_broken);
+    }
 }
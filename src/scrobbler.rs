@@ -0,0 +1,100 @@
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use id3::TagLike;
+
+use crate::history::Listen;
+
+//the Audioscrobbler portable player format that Rockbox writes and offline scrobbling tools read
+const SCROBBLER_LOG: &str = ".scrobbler.log";
+const SCROBBLER_LOG_HEADER: &str = "#AUDIOSCROBBLER/1.1\n#TZ/UTC\n#CLIENT/music-player 0.1.0\n";
+
+pub struct TrackInfo {
+    pub artist: String,
+    pub album: String,
+    pub title: String,
+    pub track_number: Option<u32>,
+}
+
+impl TrackInfo {
+    //songs without tags are expected to be named like "artist - title"
+    pub fn read(path: &Path) -> Option<Self> {
+        let song = crate::get_song_name(path);
+        let file_name = path.file_stem().and_then(|name| name.to_str()).unwrap_or(&song);
+        let (file_artist, file_title) = match file_name.split_once(" - ") {
+            Some((artist, title)) => (Some(artist.trim()), title.trim()),
+            None => (None, song.as_str()),
+        };
+        let tag = id3::v1v2::read_from_path(path).ok();
+        let tag_text = |text: Option<&str>| {
+            text.map(str::trim)
+                .filter(|text| !text.is_empty())
+                .map(String::from)
+        };
+        let artist = tag
+            .as_ref()
+            .and_then(|tag| tag_text(tag.artist()).or_else(|| tag_text(tag.album_artist())))
+            .or_else(|| file_artist.map(String::from))?;
+        let title = tag
+            .as_ref()
+            .and_then(|tag| tag_text(tag.title()))
+            .unwrap_or_else(|| file_title.to_string());
+        let album = tag
+            .as_ref()
+            .and_then(|tag| tag_text(tag.album()))
+            .unwrap_or_default();
+        Some(Self {
+            artist,
+            album,
+            title,
+            track_number: tag.as_ref().and_then(id3::Tag::track),
+        })
+    }
+}
+
+pub fn append_scrobble(path: &Path, listen: &Listen) {
+    let Some(track) = TrackInfo::read(path) else {
+        println!(
+            "{} has no artist tag and is not named like \"artist - title\", so it was not added to the scrobble log",
+            listen.song
+        );
+        return;
+    };
+    let is_new = !Path::new(SCROBBLER_LOG).exists();
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(SCROBBLER_LOG)
+        .expect("Failed to open the scrobble log");
+    if is_new {
+        file.write_all(SCROBBLER_LOG_HEADER.as_bytes())
+            .expect("Failed to write to the scrobble log");
+    }
+    //L marks a listened track, the musicbrainz track id is left empty
+    let line = [
+        clean_field(&track.artist),
+        clean_field(&track.album),
+        clean_field(&track.title),
+        track.track_number.map(|number| number.to_string()).unwrap_or_default(),
+        listen.listened_seconds.to_string(),
+        String::from("L"),
+        listen.started.to_string(),
+        String::new(),
+    ]
+    .join("\t");
+    writeln!(file, "{line}").expect("Failed to write to the scrobble log");
+}
+
+pub fn find_song_path(song: &str) -> Option<PathBuf> {
+    crate::get_song_paths()
+        .into_iter()
+        .find(|path| crate::get_song_name(path) == song)
+}
+
+//tabs and line breaks would break the columns of the log
+fn clean_field(text: &str) -> String {
+    text.replace(['\t', '\n', '\r'], " ")
+}
@@ -0,0 +1,294 @@
+use std::path::PathBuf;
+
+use crate::playlist_settings::{self, PersistentSettings, MAX_RATING};
+
+const COLUMNS: [&str; 8] = [
+    "name",
+    "path",
+    "volume",
+    "rating",
+    "starred",
+    "has_lyrics",
+    "play_count",
+    "probability_weight",
+];
+
+pub fn export_settings(file: &str, paths: &[PathBuf]) -> Result<usize, String> {
+    let settings = playlist_settings::get_persistent_settings();
+    let probabilities = settings.get_probability_distribution(paths);
+    let mut writer =
+        csv::Writer::from_path(file).map_err(|error| format!("failed to create {file} ({error})"))?;
+    let write_error = |error: csv::Error| format!("failed to write {file} ({error})");
+    writer.write_record(COLUMNS).map_err(write_error)?;
+    for (path, probability) in paths.iter().zip(probabilities) {
+        let song = crate::get_song_name(path);
+        let song_settings = settings.get_song_settings(&song);
+        writer
+            .write_record([
+                song.clone(),
+                path.display().to_string(),
+                (song_settings.song_volume * 100.0).round().to_string(),
+                song_settings.rating.map(|rating| rating.to_string()).unwrap_or_default(),
+                song_settings.is_starred().to_string(),
+                song_settings.has_lyrics().to_string(),
+                settings.get_song_play_count(&song).to_string(),
+                probability.to_string(),
+            ])
+            .map_err(write_error)?;
+    }
+    writer.flush().map_err(|error| format!("failed to write {file} ({error})"))?;
+    Ok(paths.len())
+}
+
+//a column that is missing from the file keeps the current values, so a spreadsheet may only contain the columns that are edited
+#[derive(Clone, Debug, Default)]
+struct SongChanges {
+    volume: Option<f32>,
+    change_rating: bool,
+    //None means the song is unrated
+    rating: Option<u8>,
+    has_lyrics: Option<bool>,
+    play_count: Option<u32>,
+    probability_weight: Option<u32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SettingsImport {
+    pub file: String,
+    changes: Vec<(PathBuf, SongChanges)>,
+    pub invalid_rows: Vec<String>,
+    pub unknown_songs: Vec<String>,
+}
+
+impl SettingsImport {
+    pub fn read(file: &str, paths: &[PathBuf]) -> Result<Self, String> {
+        let mut reader =
+            csv::Reader::from_path(file).map_err(|error| format!("failed to read {file} ({error})"))?;
+        let headers = reader
+            .headers()
+            .map_err(|error| format!("failed to read the header of {file} ({error})"))?
+            .iter()
+            .map(|header| header.trim().to_lowercase().replace(' ', "_"))
+            .collect::<Vec<String>>();
+        for header in &headers {
+            if !COLUMNS.contains(&header.as_str()) {
+                return Err(format!(
+                    "unknown column '{header}', the columns can be {}",
+                    COLUMNS.join(", ")
+                ));
+            }
+        }
+        if !headers.iter().any(|header| header == "name" || header == "path") {
+            return Err(String::from("the file needs a name or a path column to find the songs"));
+        }
+
+        let settings = playlist_settings::get_persistent_settings();
+        let mut changes = Vec::new();
+        let mut invalid_rows = Vec::new();
+        let mut unknown_songs = Vec::new();
+        //the header is the first line
+        for (line, record) in reader.records().enumerate().map(|(i, record)| (i + 2, record)) {
+            let record = match record {
+                Ok(record) => record,
+                Err(error) => {
+                    invalid_rows.push(format!("line {line}: {error}"));
+                    continue;
+                }
+            };
+            let get = |column: &str| {
+                headers
+                    .iter()
+                    .position(|header| header == column)
+                    .and_then(|i| record.get(i))
+                    .map(str::trim)
+            };
+            let Some(path) = find_path(get("name"), get("path"), paths) else {
+                let song = get("name").filter(|name| !name.is_empty()).or(get("path"));
+                unknown_songs.push(format!("line {line}: {}", song.unwrap_or_default()));
+                continue;
+            };
+            let song = crate::get_song_name(path);
+            match parse_changes(&get, settings.get_song_settings(&song).rating) {
+                Ok(song_changes) => changes.push((path.clone(), song_changes)),
+                Err(error) => invalid_rows.push(format!("line {line} ({song}): {error}")),
+            }
+        }
+        Ok(Self {
+            file: file.to_string(),
+            changes,
+            invalid_rows,
+            unknown_songs,
+        })
+    }
+
+    //only values that differ from the current settings are listed
+    pub fn preview(&self, paths: &[PathBuf]) -> Vec<String> {
+        let settings = playlist_settings::get_persistent_settings();
+        let probabilities = settings.get_probability_distribution(paths);
+        let mut preview = Vec::new();
+        for (path, song_changes) in &self.changes {
+            let song = crate::get_song_name(path);
+            let song_settings = settings.get_song_settings(&song);
+            let mut differences = Vec::new();
+            if let Some(volume) = song_changes.volume {
+                if (volume - song_settings.song_volume).abs() > f32::EPSILON {
+                    differences.push(format!(
+                        "volume {}% -> {}%",
+                        (song_settings.song_volume * 100.0).round(),
+                        (volume * 100.0).round()
+                    ));
+                }
+            }
+            if song_changes.change_rating && song_changes.rating != song_settings.rating {
+                differences.push(format!(
+                    "rating {} -> {}",
+                    format_optional_rating(song_settings.rating),
+                    format_optional_rating(song_changes.rating)
+                ));
+            }
+            if let Some(has_lyrics) = song_changes.has_lyrics {
+                if has_lyrics != song_settings.has_lyrics() {
+                    differences.push(format!("has lyrics {} -> {has_lyrics}", song_settings.has_lyrics()));
+                }
+            }
+            if let Some(play_count) = song_changes.play_count {
+                let current_play_count = settings.get_song_play_count(&song);
+                if play_count != current_play_count {
+                    differences.push(format!("play count {current_play_count} -> {play_count}"));
+                }
+            }
+            if let Some(probability_weight) = song_changes.probability_weight {
+                let current_weight = paths
+                    .iter()
+                    .position(|other| other == path)
+                    .map_or(0, |i| probabilities[i]);
+                if probability_weight != current_weight {
+                    differences.push(format!("probability weight {current_weight} -> {probability_weight}"));
+                }
+            }
+            if !differences.is_empty() {
+                preview.push(format!("{song}: {}", differences.join(", ")));
+            }
+        }
+        preview
+    }
+
+    pub fn apply(&self, settings: &mut PersistentSettings) {
+        for (path, song_changes) in &self.changes {
+            let song = crate::get_song_name(path);
+            let mut song_settings = settings.get_song_settings(&song);
+            if let Some(volume) = song_changes.volume {
+                song_settings.song_volume = volume;
+            }
+            if song_changes.change_rating {
+                song_settings.rating = song_changes.rating;
+            }
+            match song_changes.has_lyrics {
+                Some(true) => {
+                    song_settings.add_tag(playlist_settings::LYRICS_TAG);
+                }
+                Some(false) => {
+                    song_settings.remove_tag(playlist_settings::LYRICS_TAG);
+                }
+                None => (),
+            }
+            //songs without a change to their settings don't get an entry of their own
+            if song_changes.volume.is_some()
+                || song_changes.change_rating
+                || song_changes.has_lyrics.is_some()
+            {
+                settings.set_song_settings(song.clone(), song_settings);
+            }
+            if let Some(play_count) = song_changes.play_count {
+                settings.set_song_play_count(&song, play_count);
+            }
+            if let Some(probability_weight) = song_changes.probability_weight {
+                settings.set_song_probability(path.to_str().expect("path has no name"), probability_weight);
+            }
+        }
+    }
+}
+
+fn find_path<'a>(name: Option<&str>, path: Option<&str>, paths: &'a [PathBuf]) -> Option<&'a PathBuf> {
+    let by_name = name.filter(|name| !name.is_empty()).and_then(|name| {
+        paths
+            .iter()
+            .find(|path| playlist_settings::same_name(&crate::get_song_name(path), name))
+    });
+    by_name.or_else(|| {
+        let path = path.filter(|path| !path.is_empty())?;
+        paths.iter().find(|other| other.to_str() == Some(path))
+    })
+}
+
+fn parse_changes<'a>(
+    get: &impl Fn(&str) -> Option<&'a str>,
+    current_rating: Option<u8>,
+) -> Result<SongChanges, String> {
+    let mut song_changes = SongChanges::default();
+    if let Some(volume) = get("volume").filter(|volume| !volume.is_empty()) {
+        let volume = volume
+            .trim_end_matches('%')
+            .parse::<f32>()
+            .ok()
+            .filter(|volume| (0.0..=100.0).contains(volume))
+            .ok_or_else(|| format!("the volume '{volume}' has to be a percentage from 0 to 100"))?;
+        song_changes.volume = Some(volume / 100.0);
+    }
+    //an empty rating means the song is unrated
+    if let Some(rating) = get("rating") {
+        song_changes.change_rating = true;
+        if !rating.is_empty() {
+            let rating = rating
+                .parse::<u8>()
+                .ok()
+                .filter(|rating| *rating <= MAX_RATING)
+                .ok_or_else(|| format!("the rating '{rating}' has to be a number from 0 to {MAX_RATING}"))?;
+            song_changes.rating = Some(rating);
+        }
+    }
+    //starred songs have the highest rating, the rating column is more precise, so the starred column
+    //only changes the rating of files without a rating column and only if it disagrees with the current rating
+    if let Some(starred) = get("starred").filter(|starred| !starred.is_empty()) {
+        let starred = parse_bool(starred)
+            .ok_or_else(|| format!("starred '{starred}' has to be true or false"))?;
+        let is_starred = current_rating.unwrap_or(playlist_settings::DEFAULT_RATING) == MAX_RATING;
+        if !song_changes.change_rating && starred != is_starred {
+            song_changes.change_rating = true;
+            song_changes.rating = starred.then_some(MAX_RATING);
+        }
+    }
+    if let Some(has_lyrics) = get("has_lyrics").filter(|has_lyrics| !has_lyrics.is_empty()) {
+        song_changes.has_lyrics = Some(
+            parse_bool(has_lyrics)
+                .ok_or_else(|| format!("has_lyrics '{has_lyrics}' has to be true or false"))?,
+        );
+    }
+    if let Some(play_count) = get("play_count").filter(|play_count| !play_count.is_empty()) {
+        song_changes.play_count = Some(
+            play_count
+                .parse::<u32>()
+                .map_err(|_| format!("the play count '{play_count}' has to be a positive integer"))?,
+        );
+    }
+    if let Some(weight) = get("probability_weight").filter(|weight| !weight.is_empty()) {
+        song_changes.probability_weight = Some(
+            weight
+                .parse::<u32>()
+                .map_err(|_| format!("the probability weight '{weight}' has to be a positive integer"))?,
+        );
+    }
+    Ok(song_changes)
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "y" | "1" | "x" => Some(true),
+        "false" | "no" | "n" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn format_optional_rating(rating: Option<u8>) -> String {
+    rating.map_or(String::from("unrated"), playlist_settings::format_rating)
+}
@@ -0,0 +1,228 @@
+use std::path::PathBuf;
+
+use chrono::Local;
+use rand::{rngs::StdRng, Rng};
+
+use crate::{playlist_settings::PersistentSettings, utils};
+
+pub trait ShuffleStrategy {
+    fn name(&self) -> &'static str;
+
+    //candidates are the indices of all songs that may be played, there is always at least one
+    fn select(
+        &mut self,
+        candidates: &[usize],
+        paths: &[PathBuf],
+        settings: &PersistentSettings,
+        random: &mut StdRng,
+    ) -> usize;
+
+    fn song_played(&mut self, _song: &str) {}
+
+    fn box_clone(&self) -> Box<dyn ShuffleStrategy>;
+}
+
+impl Clone for Box<dyn ShuffleStrategy> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+pub const STRATEGY_NAMES: [&str; 5] = ["weighted", "random", "bag", "least-recent", "least-played"];
+
+pub fn from_name(name: &str) -> Option<Box<dyn ShuffleStrategy>> {
+    match name.replace([' ', '-', '_'], "").as_str() {
+        "weighted" => Some(Box::new(WeightedStrategy)),
+        "random" | "uniform" => Some(Box::new(UniformStrategy)),
+        "bag" | "shufflebag" => Some(Box::new(ShuffleBagStrategy::default())),
+        "leastrecent" | "leastrecentlyplayed" => Some(Box::new(LeastRecentlyPlayedStrategy)),
+        "leastplayed" => Some(Box::new(LeastPlayedStrategy)),
+        _ => None,
+    }
+}
+
+//every song gets more likely to be chosen the more other songs were played since it was last played
+#[derive(Clone)]
+pub struct WeightedStrategy;
+
+impl ShuffleStrategy for WeightedStrategy {
+    fn name(&self) -> &'static str {
+        "weighted"
+    }
+
+    fn select(
+        &mut self,
+        candidates: &[usize],
+        paths: &[PathBuf],
+        settings: &PersistentSettings,
+        random: &mut StdRng,
+    ) -> usize {
+        let weights = get_song_weights(paths, settings);
+        let mut modified_song_probability_distribution = vec![0.0; paths.len()];
+        for &i in candidates {
+            modified_song_probability_distribution[i] = weights[i];
+        }
+        if modified_song_probability_distribution.iter().all(|p| *p <= 0.0) {
+            //only the song that was last played can be chosen
+            return candidates[0];
+        }
+        utils::weighted_random_selection(&modified_song_probability_distribution, random)
+    }
+
+    fn box_clone(&self) -> Box<dyn ShuffleStrategy> {
+        Box::new(self.clone())
+    }
+}
+
+pub fn get_song_weights(paths: &[PathBuf], settings: &PersistentSettings) -> Vec<f64> {
+    let probabilities = settings.get_probability_distribution(paths);
+    let now = Local::now().timestamp();
+    paths
+        .iter()
+        .zip(probabilities)
+        .map(|(path, songs_played_since)| {
+            let song = crate::get_song_name(path);
+            let rating = settings.get_song_settings(&song).get_rating();
+            let growth = settings.probability_growth.get_weight(
+                songs_played_since,
+                settings.get_song_last_played(&song),
+                now,
+            );
+            growth * settings.get_rating_weight(rating) as f64 * settings.get_skip_multiplier(&song)
+        })
+        .collect()
+}
+
+#[derive(Clone)]
+pub struct UniformStrategy;
+
+impl ShuffleStrategy for UniformStrategy {
+    fn name(&self) -> &'static str {
+        "random"
+    }
+
+    fn select(
+        &mut self,
+        candidates: &[usize],
+        _paths: &[PathBuf],
+        _settings: &PersistentSettings,
+        random: &mut StdRng,
+    ) -> usize {
+        candidates[random.gen_range(0..candidates.len())]
+    }
+
+    fn box_clone(&self) -> Box<dyn ShuffleStrategy> {
+        Box::new(self.clone())
+    }
+}
+
+//every song is played once before any song is played again
+#[derive(Clone, Default)]
+pub struct ShuffleBagStrategy {
+    played_songs: Vec<String>,
+}
+
+impl ShuffleStrategy for ShuffleBagStrategy {
+    fn name(&self) -> &'static str {
+        "bag"
+    }
+
+    fn select(
+        &mut self,
+        candidates: &[usize],
+        paths: &[PathBuf],
+        _settings: &PersistentSettings,
+        random: &mut StdRng,
+    ) -> usize {
+        let mut remaining_songs = candidates
+            .iter()
+            .copied()
+            .filter(|i| !self.played_songs.contains(&crate::get_song_name(&paths[*i])))
+            .collect::<Vec<usize>>();
+        if remaining_songs.is_empty() {
+            self.played_songs.clear();
+            remaining_songs = candidates.to_vec();
+        }
+        remaining_songs[random.gen_range(0..remaining_songs.len())]
+    }
+
+    fn song_played(&mut self, song: &str) {
+        if !self.played_songs.iter().any(|other| other == song) {
+            self.played_songs.push(song.to_string());
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn ShuffleStrategy> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Clone)]
+pub struct LeastRecentlyPlayedStrategy;
+
+impl ShuffleStrategy for LeastRecentlyPlayedStrategy {
+    fn name(&self) -> &'static str {
+        "least-recent"
+    }
+
+    fn select(
+        &mut self,
+        candidates: &[usize],
+        paths: &[PathBuf],
+        settings: &PersistentSettings,
+        random: &mut StdRng,
+    ) -> usize {
+        //songs that were never played come first
+        select_minimum(candidates, random, |i| {
+            settings
+                .get_song_last_played(&crate::get_song_name(&paths[i]))
+                .unwrap_or(i64::MIN)
+        })
+    }
+
+    fn box_clone(&self) -> Box<dyn ShuffleStrategy> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Clone)]
+pub struct LeastPlayedStrategy;
+
+impl ShuffleStrategy for LeastPlayedStrategy {
+    fn name(&self) -> &'static str {
+        "least-played"
+    }
+
+    fn select(
+        &mut self,
+        candidates: &[usize],
+        paths: &[PathBuf],
+        settings: &PersistentSettings,
+        random: &mut StdRng,
+    ) -> usize {
+        select_minimum(candidates, random, |i| {
+            settings.get_song_play_count(&crate::get_song_name(&paths[i])) as i64
+        })
+    }
+
+    fn box_clone(&self) -> Box<dyn ShuffleStrategy> {
+        Box::new(self.clone())
+    }
+}
+
+//ties are broken randomly so songs with the same key don't always play in folder order
+fn select_minimum(
+    candidates: &[usize],
+    random: &mut StdRng,
+    key: impl Fn(usize) -> i64,
+) -> usize {
+    let keys = candidates.iter().map(|i| key(*i)).collect::<Vec<i64>>();
+    let minimum = *keys.iter().min().expect("there are no songs to choose from");
+    let tied_songs = candidates
+        .iter()
+        .zip(keys)
+        .filter(|(_, key)| *key == minimum)
+        .map(|(i, _)| *i)
+        .collect::<Vec<usize>>();
+    tied_songs[random.gen_range(0..tied_songs.len())]
+}
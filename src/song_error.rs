@@ -0,0 +1,51 @@
+use std::{fmt::Display, io, time::Duration};
+
+use crate::utils;
+
+use rodio::decoder::DecoderError;
+
+#[derive(Debug)]
+pub enum SongError {
+    Open(io::Error),
+    Decode(DecoderError),
+    //the header could be read but decoding the frames stopped early
+    Corrupt {
+        decoded: Duration,
+        expected: Option<Duration>,
+    },
+}
+
+impl Display for SongError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SongError::Open(error) => write!(f, "failed to open the file ({error})"),
+            SongError::Decode(error) => write!(f, "failed to decode the file ({error})"),
+            SongError::Corrupt {
+                decoded,
+                expected: Some(expected),
+            } => write!(
+                f,
+                "the file is corrupt, decoding stops at {} of {}",
+                utils::format_duration(decoded),
+                utils::format_duration(expected)
+            ),
+            SongError::Corrupt { expected: None, .. } => {
+                write!(f, "the file is corrupt, no audio could be decoded")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SongError {}
+
+impl From<io::Error> for SongError {
+    fn from(error: io::Error) -> Self {
+        SongError::Open(error)
+    }
+}
+
+impl From<DecoderError> for SongError {
+    fn from(error: DecoderError) -> Self {
+        SongError::Decode(error)
+    }
+}
@@ -0,0 +1,315 @@
+use std::{
+    fmt::{Display, Write},
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{Datelike, Local, NaiveDate, TimeZone, Timelike};
+
+use crate::{
+    history::{self, ListenEnd},
+    playlist_settings, utils,
+};
+
+const TOP_ENTRIES: usize = 10;
+
+#[derive(Clone, Copy, Debug)]
+pub enum StatsRange {
+    Today,
+    Week,
+    Month,
+    Year(i32),
+    All,
+}
+
+impl StatsRange {
+    pub fn parse(range: &str) -> Option<Self> {
+        match range.replace(' ', "").to_lowercase().as_str() {
+            "" | "all" | "alltime" => Some(StatsRange::All),
+            "today" => Some(StatsRange::Today),
+            "week" | "thisweek" => Some(StatsRange::Week),
+            "month" | "thismonth" => Some(StatsRange::Month),
+            "year" | "thisyear" => Some(StatsRange::Year(Local::now().year())),
+            year => year.parse::<i32>().ok().map(StatsRange::Year),
+        }
+    }
+
+    //the start and end of the range as timestamps, the end is exclusive
+    fn get_bounds(self) -> (i64, i64) {
+        let today = Local::now().date_naive();
+        let start = match self {
+            StatsRange::Today => Some(today),
+            StatsRange::Week => today.checked_sub_days(chrono::Days::new(
+                today.weekday().num_days_from_monday() as u64,
+            )),
+            StatsRange::Month => today.with_day(1),
+            StatsRange::Year(year) => NaiveDate::from_ymd_opt(year, 1, 1),
+            StatsRange::All => None,
+        };
+        let end = match self {
+            StatsRange::Year(year) => NaiveDate::from_ymd_opt(year + 1, 1, 1),
+            _ => None,
+        };
+        (
+            start.map_or(i64::MIN, get_start_of_date),
+            end.map_or(i64::MAX, get_start_of_date),
+        )
+    }
+}
+
+impl Display for StatsRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StatsRange::Today => write!(f, "today"),
+            StatsRange::Week => write!(f, "this week"),
+            StatsRange::Month => write!(f, "this month"),
+            StatsRange::Year(year) => write!(f, "{year}"),
+            StatsRange::All => write!(f, "all time"),
+        }
+    }
+}
+
+fn get_start_of_date(date: NaiveDate) -> i64 {
+    date.and_hms_opt(0, 0, 0)
+        .and_then(|midnight| Local.from_local_datetime(&midnight).earliest())
+        .map_or(i64::MIN, |midnight| midnight.timestamp())
+}
+
+pub enum ExportFormat {
+    Html,
+    Markdown,
+}
+
+impl ExportFormat {
+    pub fn from_file_name(file: &str) -> Option<Self> {
+        let extension = Path::new(file).extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "html" | "htm" => Some(ExportFormat::Html),
+            "md" | "markdown" => Some(ExportFormat::Markdown),
+            _ => None,
+        }
+    }
+}
+
+//every section is a titled table, so the console, markdown and html output show the same numbers
+struct Section {
+    title: String,
+    header: [&'static str; 2],
+    rows: Vec<(String, String)>,
+}
+
+pub struct ListeningStats {
+    range: StatsRange,
+    sections: Vec<Section>,
+}
+
+impl ListeningStats {
+    pub fn collect(range: StatsRange, paths: &[PathBuf]) -> Self {
+        let (start, end) = range.get_bounds();
+        let listens = history::get_history()
+            .into_iter()
+            .filter(|listen| listen.started >= start && listen.started < end)
+            .collect::<Vec<_>>();
+        let settings = playlist_settings::get_persistent_settings();
+
+        let listened_seconds = listens.iter().map(|listen| listen.listened_seconds).sum::<u64>();
+        let skips = listens
+            .iter()
+            .filter(|listen| listen.end == ListenEnd::Skipped)
+            .count();
+        let completed = listens
+            .iter()
+            .filter(|listen| listen.end == ListenEnd::Completed)
+            .count();
+        let skip_rate = if listens.is_empty() {
+            0.0
+        } else {
+            skips as f64 * 100.0 / listens.len() as f64
+        };
+        let overview = Section {
+            title: String::from("Overview"),
+            header: ["", ""],
+            rows: vec![
+                (String::from("listens"), listens.len().to_string()),
+                (String::from("completed"), completed.to_string()),
+                (String::from("skipped"), format!("{skips} ({skip_rate:.0}%)")),
+                (String::from("listening time"), format_listening_time(listened_seconds)),
+            ],
+        };
+
+        let mut songs: Vec<(String, u32, u64)> = Vec::new();
+        let mut folders: Vec<(String, u32, u64)> = Vec::new();
+        let mut starred = [0, 0];
+        let mut lyrics = [0, 0];
+        let mut hours = [0; 24];
+        for listen in &listens {
+            //skipped listens are part of the listening time but not of the most played songs and folders
+            if listen.is_play(settings.play_threshold) {
+                let folder = paths
+                    .iter()
+                    .find(|path| crate::get_song_name(path) == listen.song)
+                    .and_then(|path| path.parent())
+                    .and_then(|folder| folder.file_name())
+                    .and_then(|folder| folder.to_str())
+                    .unwrap_or("(removed songs)")
+                    .to_string();
+                add_listen(&mut songs, &listen.song, listen.listened_seconds);
+                add_listen(&mut folders, &folder, listen.listened_seconds);
+            }
+            let song_settings = settings.get_song_settings(&listen.song);
+            starred[usize::from(song_settings.is_starred())] += 1;
+            lyrics[usize::from(song_settings.has_lyrics())] += 1;
+            if let Some(started) = Local.timestamp_opt(listen.started, 0).single() {
+                hours[started.hour() as usize] += listen.listened_seconds;
+            }
+        }
+        songs.sort_by(|a, b| b.1.cmp(&a.1).then(b.2.cmp(&a.2)));
+        folders.sort_by(|a, b| b.1.cmp(&a.1).then(b.2.cmp(&a.2)));
+
+        let sections = vec![
+            overview,
+            Section {
+                title: String::from("Most played songs"),
+                header: ["song", "plays"],
+                rows: get_top_rows(&songs),
+            },
+            Section {
+                title: String::from("Most played folders"),
+                header: ["folder", "plays"],
+                rows: get_top_rows(&folders),
+            },
+            Section {
+                title: String::from("Stars and lyrics"),
+                header: ["", "listens"],
+                rows: vec![
+                    (String::from("starred"), starred[1].to_string()),
+                    (String::from("not starred"), starred[0].to_string()),
+                    (String::from("with lyrics"), lyrics[1].to_string()),
+                    (String::from("without lyrics"), lyrics[0].to_string()),
+                ],
+            },
+            Section {
+                title: String::from("Listening by hour of day"),
+                header: ["hour", "listening time"],
+                rows: hours
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, seconds)| **seconds > 0)
+                    .map(|(hour, seconds)| (format!("{hour:02}:00"), format_listening_time(*seconds)))
+                    .collect(),
+            },
+        ];
+        Self { range, sections }
+    }
+
+    pub fn print(&self) {
+        println!("listening stats ({})", self.range);
+        for section in &self.sections {
+            println!("\n{}:", section.title);
+            if section.rows.is_empty() {
+                println!("nothing was played");
+            }
+            for (label, value) in &section.rows {
+                println!("{label} - {value}");
+            }
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        //writing to a string can not fail
+        let mut markdown = format!("# Listening stats ({})\n", self.range);
+        for section in &self.sections {
+            writeln!(markdown, "\n## {}\n", section.title).unwrap();
+            if section.rows.is_empty() {
+                markdown += "Nothing was played.\n";
+                continue;
+            }
+            writeln!(markdown, "| {} | {} |\n| --- | --- |", section.header[0], section.header[1]).unwrap();
+            for (label, value) in &section.rows {
+                writeln!(
+                    markdown,
+                    "| {} | {} |",
+                    label.replace('|', "\\|"),
+                    value.replace('|', "\\|")
+                )
+                .unwrap();
+            }
+        }
+        markdown
+    }
+
+    pub fn to_html(&self) -> String {
+        let title = format!("Listening stats ({})", self.range);
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>\nbody {{ font-family: sans-serif; max-width: 50em; margin: auto; }}\ntable {{ border-collapse: collapse; }}\ntd, th {{ border: 1px solid #ccc; padding: 0.3em 0.8em; text-align: left; }}\n</style>\n</head>\n<body>\n<h1>{title}</h1>\n"
+        );
+        for section in &self.sections {
+            writeln!(html, "<h2>{}</h2>", escape_html(&section.title)).unwrap();
+            if section.rows.is_empty() {
+                html += "<p>Nothing was played.</p>\n";
+                continue;
+            }
+            writeln!(
+                html,
+                "<table>\n<tr><th>{}</th><th>{}</th></tr>",
+                section.header[0], section.header[1]
+            )
+            .unwrap();
+            for (label, value) in &section.rows {
+                writeln!(
+                    html,
+                    "<tr><td>{}</td><td>{}</td></tr>",
+                    escape_html(label),
+                    escape_html(value)
+                )
+                .unwrap();
+            }
+            html += "</table>\n";
+        }
+        html += "</body>\n</html>\n";
+        html
+    }
+
+    pub fn export(&self, file: &str, format: &ExportFormat) -> Result<(), String> {
+        let contents = match format {
+            ExportFormat::Html => self.to_html(),
+            ExportFormat::Markdown => self.to_markdown(),
+        };
+        fs::write(file, contents).map_err(|error| format!("failed to write {file} ({error})"))
+    }
+}
+
+fn add_listen(entries: &mut Vec<(String, u32, u64)>, name: &str, listened_seconds: u64) {
+    match entries.iter_mut().find(|(other, _, _)| name == other) {
+        Some((_, listens, seconds)) => {
+            *listens += 1;
+            *seconds += listened_seconds;
+        }
+        None => entries.push((name.to_string(), 1, listened_seconds)),
+    }
+}
+
+fn get_top_rows(entries: &[(String, u32, u64)]) -> Vec<(String, String)> {
+    entries
+        .iter()
+        .take(TOP_ENTRIES)
+        .map(|(name, listens, seconds)| {
+            (name.clone(), format!("{listens} ({})", format_listening_time(*seconds)))
+        })
+        .collect()
+}
+
+fn format_listening_time(seconds: u64) -> String {
+    if seconds < 3600 {
+        return utils::format_duration(&Duration::from_secs(seconds));
+    }
+    format!("{}h {}min", seconds / 3600, seconds % 3600 / 60)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,173 @@
+use std::time::Duration;
+
+use rodio::{source::SeekError, Sample, Source};
+
+//plays a song between its trim points, positions and seeking are relative to the start trim point
+pub struct TrimmedSource<S> {
+    input: S,
+    start: Duration,
+    //samples of every channel that are played from the start trim point until the end trim point
+    length: Option<u64>,
+    played: u64,
+}
+
+impl<S> TrimmedSource<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    pub fn new(mut input: S, start: Duration, end: Option<Duration>) -> Self {
+        let start = if start.is_zero() {
+            start
+        } else if let Err(error) = input.try_seek(start) {
+            println!("Failed to skip the start of the song: {error}");
+            Duration::ZERO
+        } else {
+            start
+        };
+        let samples_per_second = get_samples_per_second(&input);
+        let length = end.map(|end| to_samples(end.saturating_sub(start), samples_per_second));
+        Self {
+            input,
+            start,
+            length,
+            played: 0,
+        }
+    }
+
+    fn remaining(&self) -> Option<u64> {
+        self.length.map(|length| length.saturating_sub(self.played))
+    }
+}
+
+impl<S> Iterator for TrimmedSource<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<S::Item> {
+        if self.remaining() == Some(0) {
+            return None;
+        }
+        let sample = self.input.next()?;
+        self.played += 1;
+        Some(sample)
+    }
+}
+
+impl<S> Source for TrimmedSource<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        let frame_len = self.input.current_frame_len();
+        match self.remaining().map(|remaining| usize::try_from(remaining).unwrap_or(usize::MAX)) {
+            Some(remaining) => Some(frame_len.map_or(remaining, |frame_len| frame_len.min(remaining))),
+            None => frame_len,
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        let samples_per_second = get_samples_per_second(&self.input);
+        let input_length = self
+            .input
+            .total_duration()
+            .map(|duration| duration.saturating_sub(self.start));
+        let trimmed_length = self.length.map(|length| {
+            Duration::from_secs(length / samples_per_second)
+                + Duration::from_millis(length % samples_per_second * 1000 / samples_per_second)
+        });
+        match (input_length, trimmed_length) {
+            (Some(input_length), Some(trimmed_length)) => Some(input_length.min(trimmed_length)),
+            (input_length, trimmed_length) => input_length.or(trimmed_length),
+        }
+    }
+
+    fn try_seek(&mut self, position: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(self.start + position)?;
+        self.played = to_samples(position, get_samples_per_second(&self.input));
+        Ok(())
+    }
+}
+
+fn get_samples_per_second<S>(source: &S) -> u64
+where
+    S: Source,
+    S::Item: Sample,
+{
+    (u64::from(source.sample_rate()) * u64::from(source.channels())).max(1)
+}
+
+fn to_samples(duration: Duration, samples_per_second: u64) -> u64 {
+    duration.as_secs() * samples_per_second + u64::from(duration.subsec_millis()) * samples_per_second / 1000
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rodio::{buffer::SamplesBuffer, Source};
+
+    use super::TrimmedSource;
+
+    //one channel with 10 samples per second, every sample is its own index so positions can be read from the values
+    fn ten_seconds() -> SamplesBuffer<i16> {
+        SamplesBuffer::new(1, 10, (0..100).collect::<Vec<i16>>())
+    }
+
+    #[test]
+    fn the_end_is_cut_off() {
+        let source = TrimmedSource::new(ten_seconds(), Duration::ZERO, Some(Duration::from_secs(4)));
+        assert_eq!(source.collect::<Vec<i16>>(), (0..40).collect::<Vec<i16>>());
+    }
+
+    #[test]
+    fn playing_starts_at_the_start_trim_point() {
+        let mut source = TrimmedSource::new(ten_seconds(), Duration::from_secs(2), None);
+        assert_eq!(source.next(), Some(20));
+        assert_eq!(source.count(), 79);
+        let source = TrimmedSource::new(ten_seconds(), Duration::from_secs(2), Some(Duration::from_secs(5)));
+        assert_eq!(source.collect::<Vec<i16>>(), (20..50).collect::<Vec<i16>>());
+    }
+
+    #[test]
+    fn seeking_is_relative_to_the_start_trim_point() {
+        let mut source = TrimmedSource::new(ten_seconds(), Duration::from_secs(2), Some(Duration::from_secs(5)));
+        source.try_seek(Duration::from_secs(1)).unwrap();
+        assert_eq!(source.collect::<Vec<i16>>(), (30..50).collect::<Vec<i16>>());
+    }
+
+    #[test]
+    fn seeking_past_the_end_plays_nothing() {
+        let mut source = TrimmedSource::new(ten_seconds(), Duration::from_secs(2), Some(Duration::from_secs(5)));
+        source.try_seek(Duration::from_secs(4)).unwrap();
+        assert_eq!(source.next(), None);
+        let mut source = TrimmedSource::new(ten_seconds(), Duration::ZERO, None);
+        source.try_seek(Duration::from_secs(20)).unwrap();
+        assert_eq!(source.next(), None);
+    }
+
+    #[test]
+    fn the_total_duration_is_the_trimmed_length() {
+        let total_duration = |start: u64, end: Option<u64>| {
+            TrimmedSource::new(ten_seconds(), Duration::from_secs(start), end.map(Duration::from_secs))
+                .total_duration()
+        };
+        assert_eq!(total_duration(0, None), Some(Duration::from_secs(10)));
+        assert_eq!(total_duration(2, None), Some(Duration::from_secs(8)));
+        assert_eq!(total_duration(2, Some(5)), Some(Duration::from_secs(3)));
+        //an end trim point after the end of the song doesn't make it longer
+        assert_eq!(total_duration(0, Some(20)), Some(Duration::from_secs(10)));
+    }
+}
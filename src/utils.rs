@@ -1,9 +1,10 @@
-use rand::{rngs::ThreadRng, Rng};
+use chrono::{Local, TimeZone};
+use rand::Rng;
 use std::{fs::OpenOptions, io::{self, Write}, time::Duration};
 
 pub fn weighted_random_selection(
-    probability_distribution: &[u32],
-    random: &mut ThreadRng,
+    probability_distribution: &[f64],
+    random: &mut impl Rng,
 ) -> usize {
     if probability_distribution.len() == 1 {
         return 0;
@@ -12,18 +13,21 @@ pub fn weighted_random_selection(
         !probability_distribution.is_empty(),
         "there are no songs in the playlist"
     );
-    let mut sum = probability_distribution.iter().sum::<u32>();
-    assert_ne!(
-        sum, 0,
-        "exclude lyrics mode is enabled but all songs in the playlist are set to have lyrics"
-    );
+    let sum = probability_distribution.iter().sum::<f64>();
+    assert!(sum > 0.0, "{}", crate::NO_SELECTABLE_SONGS);
+    let target = random.gen_range(0.0..sum);
+    let mut cumulative_sum = 0.0;
     for (i, p) in probability_distribution.iter().enumerate() {
-        if random.gen_bool(*p as f64 / sum as f64) {
+        cumulative_sum += p;
+        if target < cumulative_sum {
             return i;
         }
-        sum -= p;
     }
-    unreachable!();
+    //rounding errors can leave the target just above the last cumulative sum
+    probability_distribution
+        .iter()
+        .rposition(|p| *p > 0.0)
+        .expect("the sum of the probabilities is positive")
 }
 
 pub fn format_duration(duration: &Duration) -> String {
@@ -34,6 +38,16 @@ pub fn format_duration(duration: &Duration) -> String {
     }
 }
 
+pub fn get_start_of_day(timestamp: i64) -> i64 {
+    let Some(time) = Local.timestamp_opt(timestamp, 0).single() else {
+        return timestamp;
+    };
+    time.date_naive()
+        .and_hms_opt(0, 0, 0)
+        .and_then(|midnight| Local.from_local_datetime(&midnight).earliest())
+        .map_or(timestamp, |midnight| midnight.timestamp())
+}
+
 pub fn write_to_file(file: &str, contents: &str) {
     let mut file = OpenOptions::new()
         .create(true)
@@ -52,3 +66,65 @@ pub fn get_console_input() -> String {
         .expect("Failed to read input");
     input_buffer
 }
+
+//commands are matched without spaces and case, arguments like names need the original words of the input after the command
+pub fn get_arguments(input_buffer: &str, command: &str) -> String {
+    let mut command = command.chars().peekable();
+    for (index, c) in input_buffer.char_indices() {
+        if command.peek().is_none() {
+            return input_buffer[index..].trim().to_string();
+        }
+        if c.is_whitespace() {
+            continue;
+        }
+        if command.next() != Some(c.to_ascii_lowercase()) {
+            return String::new();
+        }
+    }
+    String::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::{get_arguments, weighted_random_selection};
+
+    #[test]
+    fn arguments_follow_the_command_however_it_was_typed() {
+        assert_eq!(get_arguments("smartplaylist Unheard", "smartplaylist"), "Unheard");
+        assert_eq!(get_arguments("smart playlist Unheard", "smartplaylist"), "Unheard");
+        assert_eq!(get_arguments("save filter as Deep Focus", "savefilteras"), "Deep Focus");
+        assert_eq!(get_arguments("savefilter as x", "savefilteras"), "x");
+        assert_eq!(get_arguments("importlibrary Library.xml", "importlibrary"), "Library.xml");
+        assert_eq!(get_arguments("  Mark  drop two ", "mark"), "drop two");
+        assert_eq!(get_arguments("exportstats", "exportstats"), "");
+    }
+
+    #[test]
+    fn the_same_seed_selects_the_same_songs() {
+        let probability_distribution = [1.0, 2.0, 0.0, 4.0, 8.0];
+        let select = |seed: u64| {
+            let mut random = StdRng::seed_from_u64(seed);
+            (0..50)
+                .map(|_| weighted_random_selection(&probability_distribution, &mut random))
+                .collect::<Vec<usize>>()
+        };
+        assert_eq!(select(42), select(42));
+        //a song without weight is never selected
+        assert!(!select(42).contains(&2));
+    }
+
+    #[test]
+    fn a_single_song_is_always_selected() {
+        let mut random = StdRng::seed_from_u64(0);
+        assert_eq!(weighted_random_selection(&[3.0], &mut random), 0);
+        assert_eq!(weighted_random_selection(&[0.0], &mut random), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "there are no playable songs left")]
+    fn songs_without_any_weight_cannot_be_selected() {
+        weighted_random_selection(&[0.0, 0.0, 0.0], &mut StdRng::seed_from_u64(0));
+    }
+}